@@ -8,10 +8,7 @@ use async_net::TcpStream;
 use async_sleep::impl_async_io::Timer;
 use futures_lite::future::block_on;
 
-use async_http1_lite::{
-    http::{Request, Version},
-    Http1ClientStream,
-};
+use async_http1_lite::{http::Request, Http1ClientStream};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     block_on(run())
@@ -45,26 +42,15 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
     let stream = TcpStream::connect(addr).await?;
 
     //
-    let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(stream);
-
-    let proxy_request = Request::builder()
-        .method("CONNECT")
-        .uri(format!("{domain}:{port}"))
-        .version(Version::HTTP_11)
-        .header("Host", format!("{domain}:{port}"))
-        .header("User-Agent", "async-http1-lite")
-        .header("Proxy-Connection", "Keep-Alive")
-        .body(vec![])
-        .unwrap();
-    println!("{proxy_request:?}");
-
-    stream.write_request(proxy_request).await?;
+    let stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(stream);
 
-    let (proxy_response, _) = stream.read_response().await?;
+    // Reads the bytes already read after the CONNECT response first.
+    let stream = stream
+        .connect_tunnel(format!("{domain}:{port}").parse()?, None)
+        .await?;
 
-    let (proxy_response_parts, proxy_response_body) = proxy_response.into_parts();
-    println!("{proxy_response_parts:?}");
-    println!("{:?}", String::from_utf8(proxy_response_body));
+    //
+    let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(stream);
 
     let request = Request::builder()
         .method("GET")
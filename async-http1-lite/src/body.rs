@@ -4,6 +4,21 @@ pub enum DecoderBody {
     Partial(Vec<u8>),
 }
 
+#[cfg(feature = "bytes")]
+pub enum DecoderBytesBody {
+    Completed(bytes::Bytes),
+    Partial(bytes::Bytes),
+}
+#[cfg(feature = "bytes")]
+impl From<DecoderBody> for DecoderBytesBody {
+    fn from(body: DecoderBody) -> Self {
+        match body {
+            DecoderBody::Completed(bytes) => Self::Completed(bytes.into()),
+            DecoderBody::Partial(bytes) => Self::Partial(bytes.into()),
+        }
+    }
+}
+
 pub enum EncoderBody {
     Completed(Vec<u8>),
     Partial(Vec<u8>),
@@ -0,0 +1,119 @@
+use core::{
+    cmp::min,
+    future::Future,
+    marker::PhantomData,
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::io::Error as IoError;
+
+use async_sleep::Sleepble;
+use futures_io::AsyncRead;
+use http1_spec::head_renderer::Head;
+
+use crate::{body::DecoderBody, stream::Http1StreamDecoder};
+
+//
+//
+//
+type ReadBodyFuture<'a, S, D> =
+    Pin<Box<dyn Future<Output = (&'a mut D, &'a mut S, Result<DecoderBody, IoError>)> + Send + 'a>>;
+
+/// Reads the body of the current message, until EOF of the body.
+///
+/// Dropped after EOF, the stream is ready for the next message.
+pub struct Http1BodyReader<'a, S, SLEEP, D, DH>
+where
+    S: AsyncRead + Unpin + Send,
+    SLEEP: Sleepble,
+    D: Http1StreamDecoder<S, SLEEP, DH> + Send,
+    DH: Head,
+{
+    state: State<'a, S, D>,
+    buf: Vec<u8>,
+    offset: usize,
+    phantom: PhantomData<(SLEEP, DH)>,
+}
+enum State<'a, S, D> {
+    Idle(&'a mut D, &'a mut S),
+    Reading(ReadBodyFuture<'a, S, D>),
+    Completed,
+}
+impl<'a, S, SLEEP, D, DH> Http1BodyReader<'a, S, SLEEP, D, DH>
+where
+    S: AsyncRead + Unpin + Send,
+    SLEEP: Sleepble,
+    D: Http1StreamDecoder<S, SLEEP, DH> + Send,
+    DH: Head,
+{
+    pub(crate) fn new(decoder: &'a mut D, stream: &'a mut S) -> Self {
+        Self {
+            state: State::Idle(decoder, stream),
+            buf: Vec::new(),
+            offset: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        matches!(self.state, State::Completed) && self.offset >= self.buf.len()
+    }
+}
+
+impl<'a, S, SLEEP, D, DH> AsyncRead for Http1BodyReader<'a, S, SLEEP, D, DH>
+where
+    S: AsyncRead + Unpin + Send,
+    SLEEP: Sleepble + Unpin,
+    D: Http1StreamDecoder<S, SLEEP, DH> + Send,
+    DH: Head + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.offset < this.buf.len() {
+                let n = min(buf.len(), this.buf.len() - this.offset);
+                buf[..n].copy_from_slice(&this.buf[this.offset..this.offset + n]);
+                this.offset += n;
+                return Poll::Ready(Ok(n));
+            }
+
+            match mem::replace(&mut this.state, State::Completed) {
+                State::Idle(decoder, stream) => {
+                    this.state = State::Reading(Box::pin(async move {
+                        let ret =
+                            Http1StreamDecoder::<S, SLEEP, DH>::read_body(decoder, stream).await;
+                        (decoder, stream, ret)
+                    }));
+                }
+                State::Reading(mut fut) => match fut.as_mut().poll(cx) {
+                    Poll::Ready((decoder, stream, ret)) => {
+                        let bytes = match ret {
+                            Ok(DecoderBody::Completed(bytes)) => bytes,
+                            Ok(DecoderBody::Partial(bytes)) => {
+                                this.state = State::Idle(decoder, stream);
+                                bytes
+                            }
+                            Err(err) => {
+                                this.state = State::Idle(decoder, stream);
+                                return Poll::Ready(Err(err));
+                            }
+                        };
+                        this.buf = bytes;
+                        this.offset = 0;
+                    }
+                    Poll::Pending => {
+                        this.state = State::Reading(fut);
+                        return Poll::Pending;
+                    }
+                },
+                State::Completed => return Poll::Ready(Ok(0)),
+            }
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use core::time::Duration;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+
+use async_sleep::Sleepble;
+use http::{
+    header::{
+        AUTHORIZATION, CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_LOCATION,
+        CONTENT_TYPE, COOKIE, HOST, LOCATION, PROXY_AUTHORIZATION, TRANSFER_ENCODING,
+    },
+    HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Uri, Version,
+};
+
+use crate::pool::{Connector, Http1ClientPool, Origin};
+
+//
+//
+//
+#[derive(Debug, Clone)]
+pub struct Http1ClientConfig {
+    /// Redirects followed before failing, 0 returns the redirect responses as is.
+    pub max_redirects: usize,
+    /// Retries of idempotent requests when a reused connection turns out to be closed.
+    pub max_retries: usize,
+    /// Deadline of the whole send, including the redirects and the retries.
+    pub timeout: Option<Duration>,
+    /// Per read of the stream, see Http1Stream::set_read_timeout.
+    pub read_timeout: Option<Duration>,
+    /// Per write of the stream, see Http1Stream::set_write_timeout.
+    pub write_timeout: Option<Duration>,
+}
+impl Default for Http1ClientConfig {
+    fn default() -> Self {
+        Self {
+            max_redirects: 10,
+            max_retries: 1,
+            timeout: None,
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+}
+
+/// Sends requests with absolute uris through the pool.
+pub struct Http1Client<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    pool: Http1ClientPool<C, SLEEP>,
+    config: Http1ClientConfig,
+}
+impl<C, SLEEP> Clone for Http1Client<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+impl<C, SLEEP> Http1Client<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    pub fn new(connector: C) -> Self {
+        Self::with(Http1ClientPool::new(connector), Default::default())
+    }
+    pub fn with(pool: Http1ClientPool<C, SLEEP>, config: Http1ClientConfig) -> Self {
+        Self { pool, config }
+    }
+
+    pub fn get_pool(&self) -> &Http1ClientPool<C, SLEEP> {
+        &self.pool
+    }
+    pub fn get_config(&self) -> &Http1ClientConfig {
+        &self.config
+    }
+
+    /// The Host header is set from the uri, also after redirects.
+    pub async fn send(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, IoError> {
+        match self.config.timeout {
+            Some(dur) => {
+                async_sleep::timeout::timeout::<SLEEP, _>(dur, Box::pin(self.send0(request)))
+                    .await
+                    .map_err(|err| IoError::new(IoErrorKind::TimedOut, err))?
+            }
+            None => self.send0(request).await,
+        }
+    }
+
+    async fn send0(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, IoError> {
+        let (parts, body) = request.into_parts();
+        let mut target = Target {
+            method: parts.method,
+            uri: parts.uri,
+            version: parts.version,
+            headers: parts.headers,
+            body,
+        };
+
+        let mut n_redirects = 0;
+        loop {
+            let response = self.send_with_retries(&target).await?;
+
+            let location = match response.headers().get(LOCATION) {
+                Some(location) if is_redirect(response.status()) => location,
+                _ => return Ok(response),
+            };
+            if self.config.max_redirects == 0 {
+                return Ok(response);
+            }
+            if n_redirects >= self.config.max_redirects {
+                return Err(IoError::other("too many redirects"));
+            }
+            n_redirects += 1;
+
+            let uri = resolve_location(&target.uri, location)?;
+            target.redirect(response.status(), uri)?;
+        }
+    }
+
+    async fn send_with_retries(&self, target: &Target) -> Result<Response<Vec<u8>>, IoError> {
+        let origin = Origin::from_uri(&target.uri)?;
+
+        let mut n_retries = 0;
+        loop {
+            let mut conn = self.pool.acquire(&origin).await?;
+            if let Some(dur) = self.config.read_timeout {
+                conn.set_read_timeout(dur);
+            }
+            if let Some(dur) = self.config.write_timeout {
+                conn.set_write_timeout(dur);
+            }
+
+            let ret = async {
+                conn.write_request(target.to_request()?).await?;
+                conn.read_response().await
+            }
+            .await;
+
+            match ret {
+                Ok((response, _)) => break Ok(response),
+                Err(err)
+                    if conn.is_reused()
+                        && is_stale_connection_error(&err)
+                        && target.method.is_idempotent()
+                        && n_retries < self.config.max_retries =>
+                {
+                    n_retries += 1;
+                    continue;
+                }
+                Err(err) => break Err(err),
+            }
+        }
+    }
+}
+
+//
+//
+//
+struct Target {
+    method: Method,
+    uri: Uri,
+    version: Version,
+    headers: HeaderMap<HeaderValue>,
+    body: Vec<u8>,
+}
+impl Target {
+    // The request in origin-form, e.g. `GET /foo?bar HTTP/1.1`.
+    fn to_request(&self) -> Result<Request<Vec<u8>>, IoError> {
+        let authority = self
+            .uri
+            .authority()
+            .ok_or_else(|| IoError::new(IoErrorKind::InvalidInput, "missing authority"))?;
+        let host = match authority.port() {
+            Some(port) => format!("{}:{}", authority.host(), port),
+            None => authority.host().to_owned(),
+        };
+
+        let mut headers = self.headers.to_owned();
+        headers.insert(
+            HOST,
+            HeaderValue::from_str(&host)
+                .map_err(|err| IoError::new(IoErrorKind::InvalidInput, err))?,
+        );
+
+        let mut request = Request::builder()
+            .method(self.method.to_owned())
+            .uri(self.uri.path_and_query().map(|x| x.as_str()).unwrap_or("/"))
+            .version(self.version)
+            .body(self.body.to_owned())
+            .map_err(|err| IoError::new(IoErrorKind::InvalidInput, err))?;
+        *request.headers_mut() = headers;
+
+        Ok(request)
+    }
+
+    // ref https://www.rfc-editor.org/rfc/rfc9110#section-15.4
+    fn redirect(&mut self, status: StatusCode, uri: Uri) -> Result<(), IoError> {
+        let is_method_changed = match status {
+            StatusCode::SEE_OTHER => self.method != Method::HEAD,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => self.method == Method::POST,
+            _ => false,
+        };
+        if is_method_changed {
+            self.method = Method::GET;
+            self.body.clear();
+            for name in [
+                CONTENT_LENGTH,
+                CONTENT_TYPE,
+                CONTENT_ENCODING,
+                CONTENT_LANGUAGE,
+                CONTENT_LOCATION,
+                TRANSFER_ENCODING,
+            ] {
+                self.headers.remove(name);
+            }
+        }
+
+        // Credentials are not leaked to another origin.
+        if Origin::from_uri(&self.uri)? != Origin::from_uri(&uri)? {
+            for name in [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE] {
+                self.headers.remove(name);
+            }
+        }
+
+        self.uri = uri;
+
+        Ok(())
+    }
+}
+
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+fn is_stale_connection_error(err: &IoError) -> bool {
+    matches!(
+        err.kind(),
+        IoErrorKind::UnexpectedEof
+            | IoErrorKind::ConnectionReset
+            | IoErrorKind::ConnectionAborted
+            | IoErrorKind::BrokenPipe
+    )
+}
+
+// ref https://www.rfc-editor.org/rfc/rfc3986#section-5.2, without the dot-segments removal.
+fn resolve_location(base: &Uri, location: &HeaderValue) -> Result<Uri, IoError> {
+    let location = location
+        .to_str()
+        .map_err(|err| IoError::new(IoErrorKind::InvalidData, err))?;
+    let location = location.split('#').next().unwrap_or_default();
+
+    let scheme = base.scheme_str().unwrap_or("http");
+    let authority = base.authority().map(|x| x.as_str()).unwrap_or_default();
+
+    let uri = if has_scheme(location) {
+        location.to_owned()
+    } else if location.starts_with("//") {
+        format!("{scheme}:{location}")
+    } else if location.starts_with('/') {
+        format!("{scheme}://{authority}{location}")
+    } else if location.starts_with('?') {
+        format!("{scheme}://{authority}{}{location}", base.path())
+    } else {
+        let path = base.path();
+        let dir = &path[..path.rfind('/').map(|i| i + 1).unwrap_or(0)];
+        format!("{scheme}://{authority}{dir}{location}")
+    };
+
+    uri.parse()
+        .map_err(|err| IoError::new(IoErrorKind::InvalidData, err))
+}
+
+fn has_scheme(location: &str) -> bool {
+    match location.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.starts_with(|x: char| x.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|x| x.is_ascii_alphanumeric() || matches!(x, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
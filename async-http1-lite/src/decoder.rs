@@ -1,5 +1,6 @@
 use core::{
     cmp::min,
+    mem,
     ops::{Deref, DerefMut},
     time::Duration,
 };
@@ -8,10 +9,11 @@ use std::io::{BufReader, Error as IoError, ErrorKind as IoErrorKind};
 use async_sleep::{rw::AsyncReadWithTimeoutExt as _, Sleepble};
 use async_trait::async_trait;
 use futures_io::AsyncRead;
-use http::{Request, Response, Version};
+use http::{HeaderMap, HeaderValue, Method, Request, Response, Version};
 use http1_spec::{
-    body_framing::{BodyFraming, BodyFramingDetector},
+    body_framing::BodyFraming,
     body_parser::{BodyParseOutput, BodyParser},
+    chunked_body_parser::{ChunkExtensionsCallback, ChunkedBodyParser},
     content_length_body_parser::ContentLengthBodyParser,
     head_parser::{HeadParseConfig, HeadParseOutput, HeadParser},
     request_head_parser::RequestHeadParser,
@@ -19,8 +21,48 @@ use http1_spec::{
     ReasonPhrase,
 };
 
+#[cfg(feature = "bytes")]
+use crate::body::DecoderBytesBody;
 use crate::{body::DecoderBody, stream::Http1StreamDecoder};
 
+const BUF_MAX_CAPACITY: usize = 64 * 1024;
+
+#[cfg(feature = "bytes")]
+type Buf = bytes::BytesMut;
+#[cfg(not(feature = "bytes"))]
+type Buf = Vec<u8>;
+
+#[cfg(feature = "bytes")]
+fn zeroed_buf(capacity: usize) -> Buf {
+    bytes::BytesMut::zeroed(capacity)
+}
+#[cfg(not(feature = "bytes"))]
+fn zeroed_buf(capacity: usize) -> Buf {
+    vec![0u8; capacity]
+}
+
+//
+//
+//
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The buf reached the max capacity while reading the head, e.g. respond with 431.
+    HeadTooLarge,
+    /// The buf reached the max capacity while reading the body, e.g. respond with 413.
+    BodyTooLarge,
+}
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+impl std::error::Error for DecodeError {}
+impl From<DecodeError> for IoError {
+    fn from(err: DecodeError) -> IoError {
+        IoError::new(IoErrorKind::InvalidInput, err)
+    }
+}
+
 //
 //
 //
@@ -30,34 +72,38 @@ where
 {
     head_parser: HP,
     content_length_body_parser: ContentLengthBodyParser,
-    buf: Vec<u8>,
+    chunked_body_parser: ChunkedBodyParser,
+    buf: Buf,
+    buf_max_capacity: usize,
     offset_read: usize,
     offset_parsed: usize,
     read_timeout: Duration,
     state: State,
     require_read: bool,
 }
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Default)]
 enum State {
+    #[default]
     Idle,
     ReadingHead,
     ReadBody(BodyFraming),
 }
-impl Default for State {
-    fn default() -> Self {
-        Self::Idle
-    }
-}
 impl<HP> Http1Decoder<HP>
 where
     HP: HeadParser,
 {
     //
     fn new(buf_capacity: usize, config: Option<HeadParseConfig>) -> Self {
+        let config = config.unwrap_or_default();
+        let mut chunked_body_parser = ChunkedBodyParser::new();
+        chunked_body_parser.set_trailers_config(config.clone());
+
         Self {
-            head_parser: HP::with_config(config.unwrap_or_default()),
+            head_parser: HP::with_config(config),
             content_length_body_parser: ContentLengthBodyParser::new(),
-            buf: vec![0u8; buf_capacity],
+            chunked_body_parser,
+            buf: zeroed_buf(buf_capacity),
+            buf_max_capacity: buf_capacity.max(BUF_MAX_CAPACITY),
             offset_read: 0,
             offset_parsed: 0,
             read_timeout: Duration::from_secs(5),
@@ -70,9 +116,37 @@ where
     fn set_read_timeout(&mut self, dur: Duration) {
         self.read_timeout = dur;
     }
+    /// The buf grows up to this value when the unparsed bytes fill it.
+    pub fn set_buf_max_capacity(&mut self, value: usize) {
+        self.buf_max_capacity = value;
+    }
+    pub fn get_buf_max_capacity(&self) -> usize {
+        self.buf_max_capacity
+    }
+    pub fn set_chunk_max_len(&mut self, value: usize) {
+        self.chunked_body_parser.set_chunk_max_len(value);
+    }
+    pub fn set_chunk_extensions_callback(&mut self, callback: Option<ChunkExtensionsCallback>) {
+        self.chunked_body_parser
+            .set_chunk_extensions_callback(callback);
+    }
+    pub fn get_trailers(&self) -> &HeaderMap<HeaderValue> {
+        &self.chunked_body_parser.trailers
+    }
     pub fn has_unparsed_bytes(&self) -> bool {
         self.offset_read > self.offset_parsed
     }
+    /// The bytes read from the stream but not belong to any message, e.g. after an upgrade.
+    pub fn take_unparsed_bytes(&mut self) -> Vec<u8> {
+        let bytes = self.buf[self.offset_parsed..self.offset_read].to_vec();
+        self.offset_read = 0;
+        self.offset_parsed = 0;
+        self.require_read = true;
+        bytes
+    }
+    pub fn is_idle(&self) -> bool {
+        self.state == State::Idle
+    }
 
     //
     async fn read<S: AsyncRead + Unpin, SLEEP: Sleepble>(
@@ -83,27 +157,53 @@ where
             return Ok(());
         }
 
+        match self.read_maybe_eof::<_, SLEEP>(stream).await? {
+            0 => Err(IoError::new(IoErrorKind::UnexpectedEof, "read 0")),
+            _ => Ok(()),
+        }
+    }
+
+    async fn read_maybe_eof<S: AsyncRead + Unpin, SLEEP: Sleepble>(
+        &mut self,
+        stream: &mut S,
+    ) -> Result<usize, IoError> {
         //
         if self.offset_read >= self.buf.len() {
-            return Err(IoError::new(IoErrorKind::InvalidInput, "override buf"));
+            if self.offset_parsed > 0 {
+                self.rotate_offset();
+            } else if self.buf.len() < self.buf_max_capacity {
+                let capacity = (self.buf.len() * 2).clamp(1, self.buf_max_capacity);
+                self.buf.resize(capacity, 0);
+            } else if let State::ReadBody(_) = self.state {
+                return Err(DecodeError::BodyTooLarge.into());
+            } else {
+                return Err(DecodeError::HeadTooLarge.into());
+            }
         }
 
         //
-        let n_read = match stream
+        let n_read = stream
             .read_with_timeout::<SLEEP>(&mut self.buf[self.offset_read..], self.read_timeout)
-            .await
-        {
-            Ok(n) if n == 0 => return Err(IoError::new(IoErrorKind::UnexpectedEof, "read 0")),
-            Ok(n) => n,
-            Err(err) => return Err(err),
-        };
+            .await?;
         self.offset_read += n_read;
-        Ok(())
+        Ok(n_read)
+    }
+
+    /// Reads bytes of the next message without parsing them, returns 0 at EOF.
+    ///
+    /// Cancel safe, nothing is lost when the future is dropped before it completes.
+    pub(crate) async fn fill_buf<S: AsyncRead + Unpin, SLEEP: Sleepble>(
+        &mut self,
+        stream: &mut S,
+    ) -> Result<usize, IoError> {
+        let n = self.read_maybe_eof::<_, SLEEP>(stream).await?;
+        self.require_read = self.offset_parsed == self.offset_read;
+        Ok(n)
     }
 
     fn rotate_offset(&mut self) {
         let n = self.offset_parsed;
-        self.buf.rotate_left(n);
+        self.buf.copy_within(n..self.offset_read, 0);
         self.offset_read -= n;
         self.offset_parsed = 0;
     }
@@ -124,16 +224,13 @@ where
             match self.head_parser.parse(&mut buf_reader) {
                 Ok(HeadParseOutput::Completed(n_parsed)) => {
                     self.offset_parsed += n_parsed;
-                    if self.offset_parsed == self.offset_read {
-                        self.require_read = true;
-                    } else {
-                        self.require_read = false;
-                    }
+                    self.require_read = self.offset_parsed == self.offset_read;
+
+                    self.chunked_body_parser.trailers.clear();
 
-                    let headers = self.head_parser.get_headers();
                     let version = self.head_parser.get_version();
 
-                    let body_framing = (headers, version).detect()?;
+                    let body_framing = self.head_parser.detect_body_framing()?;
                     match &body_framing {
                         BodyFraming::Neither => {
                             self.state = State::Idle;
@@ -152,10 +249,10 @@ where
                                     "Only valid in HTTP/1.1",
                                 ));
                             }
-                            return Err(IoError::new(
-                                IoErrorKind::InvalidInput,
-                                "unimplemented now",
-                            ));
+                            self.state = State::ReadBody(body_framing.clone());
+                        }
+                        BodyFraming::CloseDelimited => {
+                            self.state = State::ReadBody(body_framing.clone());
                         }
                     }
 
@@ -182,6 +279,7 @@ where
     ) -> Result<DecoderBody, IoError> {
         #[allow(clippy::single_match)]
         match self.state {
+            State::ReadBody(BodyFraming::CloseDelimited) => {}
             State::ReadBody(_) => {
                 self.read::<_, SLEEP>(stream).await?;
             }
@@ -190,7 +288,7 @@ where
 
         match &mut self.state {
             State::Idle => Ok(DecoderBody::Completed(Vec::<u8>::new())),
-            State::ReadingHead => Err(IoError::new(IoErrorKind::Other, "state should is ReadBody")),
+            State::ReadingHead => Err(IoError::other("state should is ReadBody")),
             State::ReadBody(body_framing) => match body_framing.clone() {
                 BodyFraming::Neither => unreachable!(),
                 BodyFraming::ContentLength(content_length) => {
@@ -207,11 +305,7 @@ where
                     {
                         Ok(BodyParseOutput::Completed(n_parsed)) => {
                             self.offset_parsed += n_parsed;
-                            if self.offset_parsed == self.offset_read {
-                                self.require_read = true;
-                            } else {
-                                self.require_read = false;
-                            }
+                            self.require_read = self.offset_parsed == self.offset_read;
 
                             self.state = State::Idle;
 
@@ -219,11 +313,7 @@ where
                         }
                         Ok(BodyParseOutput::Partial(n_parsed)) => {
                             self.offset_parsed += n_parsed;
-                            if self.offset_parsed == self.offset_read {
-                                self.require_read = true;
-                            } else {
-                                self.require_read = false;
-                            }
+                            self.require_read = self.offset_parsed == self.offset_read;
 
                             body_framing.update_content_length_value(content_length - n_parsed)?;
 
@@ -233,13 +323,126 @@ where
                     }
                 }
                 BodyFraming::Chunked => {
-                    Err(IoError::new(IoErrorKind::InvalidInput, "unimplemented now"))
+                    let mut body_buf = Vec::new();
+                    loop {
+                        let mut buf_reader =
+                            BufReader::new(&self.buf[self.offset_parsed..self.offset_read]);
+                        match self
+                            .chunked_body_parser
+                            .parse(&mut buf_reader, &mut body_buf)
+                        {
+                            Ok(BodyParseOutput::Completed(n_parsed)) => {
+                                self.offset_parsed += n_parsed;
+                                self.require_read = self.offset_parsed == self.offset_read;
+
+                                self.state = State::Idle;
+
+                                break Ok(DecoderBody::Completed(body_buf));
+                            }
+                            Ok(BodyParseOutput::Partial(n_parsed)) => {
+                                self.offset_parsed += n_parsed;
+
+                                // The parser stops at the end of its data buf, keep going.
+                                if n_parsed > 0 && self.offset_parsed < self.offset_read {
+                                    continue;
+                                }
+
+                                self.require_read = true;
+
+                                if !body_buf.is_empty() {
+                                    break Ok(DecoderBody::Partial(body_buf));
+                                }
+
+                                self.read::<_, SLEEP>(stream).await?;
+                            }
+                            Err(err) => break Err(err.into()),
+                        }
+                    }
+                }
+                BodyFraming::CloseDelimited => {
+                    if self.require_read && self.read_maybe_eof::<_, SLEEP>(stream).await? == 0 {
+                        self.state = State::Idle;
+
+                        return Ok(DecoderBody::Completed(Vec::<u8>::new()));
+                    }
+
+                    // All the bytes until close belong to the body.
+                    let body_buf = self.buf[self.offset_parsed..self.offset_read].to_vec();
+                    self.offset_read = 0;
+                    self.offset_parsed = 0;
+                    self.require_read = true;
+
+                    Ok(DecoderBody::Partial(body_buf))
                 }
             },
         }
     }
 }
 
+#[cfg(feature = "bytes")]
+impl<HP> Http1Decoder<HP>
+where
+    HP: HeadParser,
+{
+    async fn read_body_bytes0<S: AsyncRead + Unpin, SLEEP: Sleepble>(
+        &mut self,
+        stream: &mut S,
+    ) -> Result<DecoderBytesBody, IoError> {
+        match &self.state {
+            State::ReadBody(BodyFraming::ContentLength(content_length)) => {
+                let content_length = *content_length;
+                debug_assert!(content_length > 0);
+
+                self.read::<_, SLEEP>(stream).await?;
+
+                let n = min(self.offset_read - self.offset_parsed, content_length);
+                let bytes = self.split_parsed_bytes(n);
+                if n == content_length {
+                    self.state = State::Idle;
+
+                    Ok(DecoderBytesBody::Completed(bytes))
+                } else {
+                    self.state = State::ReadBody(BodyFraming::ContentLength(content_length - n));
+
+                    Ok(DecoderBytesBody::Partial(bytes))
+                }
+            }
+            State::ReadBody(BodyFraming::CloseDelimited) => {
+                if self.require_read && self.read_maybe_eof::<_, SLEEP>(stream).await? == 0 {
+                    self.state = State::Idle;
+
+                    return Ok(DecoderBytesBody::Completed(bytes::Bytes::new()));
+                }
+
+                let n = self.offset_read - self.offset_parsed;
+                Ok(DecoderBytesBody::Partial(self.split_parsed_bytes(n)))
+            }
+            // Chunked bodies are copied out of the chunk framing anyway.
+            _ => self.read_body0::<_, SLEEP>(stream).await.map(Into::into),
+        }
+    }
+
+    fn split_parsed_bytes(&mut self, n: usize) -> bytes::Bytes {
+        let capacity = self.buf.len();
+
+        drop(self.buf.split_to(self.offset_parsed));
+        let bytes = self.buf.split_to(n).freeze();
+
+        self.offset_read -= self.offset_parsed + n;
+        self.offset_parsed = 0;
+        self.require_read = self.offset_read == 0;
+
+        // Keeps reading into the rest of the shared allocation, refill only when it runs low.
+        // The allocation is reclaimed if the handed out bytes are dropped, otherwise only the unparsed bytes are copied.
+        if self.buf.len() < capacity / 2 {
+            self.buf.truncate(self.offset_read);
+            self.buf.resize(capacity, 0);
+        }
+
+        bytes
+    }
+}
+
 //
 //
 //
@@ -277,16 +480,20 @@ where
         let body_framing = self.read_head0::<_, SLEEP>(stream).await?;
 
         let mut request = Request::new(());
-        *request.method_mut() = self.inner.head_parser.method.to_owned();
-        *request.uri_mut() = self.inner.head_parser.uri.to_owned();
-        *request.version_mut() = self.inner.head_parser.http_version.to_owned();
-        *request.headers_mut() = self.inner.head_parser.headers.to_owned();
+        *request.method_mut() = mem::take(&mut self.inner.head_parser.method);
+        *request.uri_mut() = mem::take(&mut self.inner.head_parser.uri);
+        *request.version_mut() = self.inner.head_parser.http_version;
+        *request.headers_mut() = mem::take(&mut self.inner.head_parser.headers);
 
         Ok((request, body_framing))
     }
     async fn read_body(&mut self, stream: &mut S) -> Result<DecoderBody, IoError> {
         self.read_body0::<_, SLEEP>(stream).await
     }
+    #[cfg(feature = "bytes")]
+    async fn read_body_bytes(&mut self, stream: &mut S) -> Result<DecoderBytesBody, IoError> {
+        self.read_body_bytes0::<_, SLEEP>(stream).await
+    }
 
     fn set_read_timeout(&mut self, dur: Duration) {
         self.inner.set_read_timeout(dur)
@@ -318,6 +525,11 @@ impl Http1ResponseDecoder {
             inner: Http1ResponseDecoderInner::new(buf_capacity, config),
         }
     }
+
+    /// The method of the request that the next response answers, e.g. a response to HEAD has no body.
+    pub fn set_request_method(&mut self, method: Option<Method>) {
+        self.inner.head_parser.set_request_method(method)
+    }
 }
 
 #[async_trait]
@@ -333,17 +545,26 @@ where
         let body_framing = self.read_head0::<_, SLEEP>(stream).await?;
 
         let mut response = Response::new(());
-        *response.version_mut() = self.inner.head_parser.http_version.to_owned();
-        *response.status_mut() = self.inner.head_parser.status_code.to_owned();
-        *response.headers_mut() = self.inner.head_parser.headers.to_owned();
+        *response.version_mut() = self.inner.head_parser.http_version;
+        *response.status_mut() = self.inner.head_parser.status_code;
+        *response.headers_mut() = mem::take(&mut self.inner.head_parser.headers);
 
-        let reason_phrase = self.inner.head_parser.reason_phrase.to_owned();
+        let reason_phrase = self.inner.head_parser.reason_phrase.take();
+
+        // Interim responses answer the same request as the final one.
+        if !response.status().is_informational() {
+            self.inner.head_parser.set_request_method(None);
+        }
 
         Ok(((response, reason_phrase), body_framing))
     }
     async fn read_body(&mut self, stream: &mut S) -> Result<DecoderBody, IoError> {
         self.read_body0::<_, SLEEP>(stream).await
     }
+    #[cfg(feature = "bytes")]
+    async fn read_body_bytes(&mut self, stream: &mut S) -> Result<DecoderBytesBody, IoError> {
+        self.read_body_bytes0::<_, SLEEP>(stream).await
+    }
 
     fn set_read_timeout(&mut self, dur: Duration) {
         self.inner.set_read_timeout(dur)
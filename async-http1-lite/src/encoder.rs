@@ -12,14 +12,14 @@ use http::{
     header::{CONTENT_LENGTH, TRANSFER_ENCODING},
     request::Parts as RequestParts,
     response::Parts as ResponseParts,
-    HeaderMap, HeaderValue, Request, Response, Version,
+    HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Version,
 };
 use http1_spec::{
     body_framing::BodyFraming,
     head_renderer::{Head, HeadRenderer},
     request_head_renderer::RequestHeadRenderer,
     response_head_renderer::ResponseHeadRenderer,
-    ReasonPhrase, CHUNKED,
+    ReasonPhrase, CHUNKED, COLON, CRLF, LAST_CHUNK,
 };
 
 use crate::{body::EncoderBody, stream::Http1StreamEncoder};
@@ -34,20 +34,17 @@ where
 {
     head_renderer: HR,
     buf: Vec<u8>,
+    trailers: HeaderMap<HeaderValue>,
     write_timeout: Duration,
     state: State,
     phantom: PhantomData<H>,
 }
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Default)]
 enum State {
+    #[default]
     Idle,
     WriteBody(BodyFraming),
 }
-impl Default for State {
-    fn default() -> Self {
-        Self::Idle
-    }
-}
 impl<H, HR> Http1Encoder<H, HR>
 where
     H: Head,
@@ -58,6 +55,7 @@ where
         Self {
             head_renderer: HR::new(),
             buf: Vec::with_capacity(buf_capacity),
+            trailers: HeaderMap::new(),
             write_timeout: Duration::from_secs(5),
             state: Default::default(),
             phantom: PhantomData,
@@ -68,6 +66,13 @@ where
     fn set_write_timeout(&mut self, dur: Duration) {
         self.write_timeout = dur;
     }
+    /// Written after the last-chunk of the current chunked body.
+    pub fn set_trailers(&mut self, trailers: HeaderMap<HeaderValue>) {
+        self.trailers = trailers;
+    }
+    pub fn is_idle(&self) -> bool {
+        self.state == State::Idle
+    }
 
     //
     fn update_headers(
@@ -77,7 +82,7 @@ where
         body_framing: &BodyFraming,
     ) -> Result<(), IoError> {
         match body_framing {
-            BodyFraming::Neither => {
+            BodyFraming::Neither | BodyFraming::CloseDelimited => {
                 headers.remove(CONTENT_LENGTH);
                 headers.remove(TRANSFER_ENCODING);
             }
@@ -88,8 +93,7 @@ where
                 } else {
                     headers.insert(
                         CONTENT_LENGTH,
-                        HeaderValue::from_str(&format!("{n}"))
-                            .map_err(|err| IoError::new(IoErrorKind::Other, err))?,
+                        HeaderValue::from_str(&format!("{n}")).map_err(IoError::other)?,
                     );
                     if version == &Version::HTTP_11 {
                         if let Some(header_value) = headers.get(&TRANSFER_ENCODING) {
@@ -107,8 +111,7 @@ where
                 headers.remove(CONTENT_LENGTH);
                 headers.insert(
                     TRANSFER_ENCODING,
-                    HeaderValue::from_str(CHUNKED)
-                        .map_err(|err| IoError::new(IoErrorKind::Other, err))?,
+                    HeaderValue::from_str(CHUNKED).map_err(IoError::other)?,
                 );
             }
         }
@@ -120,7 +123,7 @@ where
         self.head_renderer.render(head, &mut self.buf)
     }
 
-    async fn write_head0<S: AsyncWrite + Unpin, SLEEP: Sleepble>(
+    async fn write_buf0<S: AsyncWrite + Unpin, SLEEP: Sleepble>(
         &self,
         stream: &mut S,
     ) -> Result<(), IoError> {
@@ -145,10 +148,7 @@ where
     ) -> Result<(), IoError> {
         match &mut self.state {
             State::Idle => {
-                return Err(IoError::new(
-                    IoErrorKind::Other,
-                    "state should is WriteBody",
-                ));
+                return Err(IoError::other("state should is WriteBody"));
             }
             State::WriteBody(body_framing) => match body_framing.clone() {
                 BodyFraming::Neither => {}
@@ -200,8 +200,61 @@ where
                         }
                     };
                 }
+                BodyFraming::CloseDelimited => {
+                    let (bytes, is_completed) = match &body {
+                        EncoderBody::Completed(bytes) => (bytes, true),
+                        EncoderBody::Partial(bytes) => (bytes, false),
+                    };
+
+                    let mut n_write = 0;
+                    while !bytes[n_write..].is_empty() {
+                        let n = stream
+                            .write_with_timeout::<SLEEP>(&bytes[n_write..], self.write_timeout)
+                            .await?;
+                        n_write += n;
+
+                        if n == 0 {
+                            return Err(IoErrorKind::WriteZero.into());
+                        }
+                    }
+
+                    // The caller closes the connection to end the body.
+                    if is_completed {
+                        self.state = State::Idle;
+                    }
+                }
                 BodyFraming::Chunked => {
-                    return Err(IoError::new(IoErrorKind::InvalidInput, "unimplemented now"))
+                    let (bytes, is_completed) = match &body {
+                        EncoderBody::Completed(bytes) => (bytes, true),
+                        EncoderBody::Partial(bytes) => (bytes, false),
+                    };
+
+                    self.buf.clear();
+                    // An empty chunk would be taken as the last-chunk, so skip it.
+                    if !bytes.is_empty() {
+                        self.buf
+                            .extend_from_slice(format!("{:X}", bytes.len()).as_bytes());
+                        self.buf.extend_from_slice(CRLF);
+                        self.buf.extend_from_slice(bytes);
+                        self.buf.extend_from_slice(CRLF);
+                    }
+                    if is_completed {
+                        self.buf.extend_from_slice(LAST_CHUNK);
+                        for (k, v) in &self.trailers {
+                            self.buf.extend_from_slice(k.as_str().as_bytes());
+                            self.buf.extend_from_slice(&[COLON]);
+                            self.buf.extend_from_slice(v.as_bytes());
+                            self.buf.extend_from_slice(CRLF);
+                        }
+                        self.buf.extend_from_slice(CRLF);
+                        self.trailers.clear();
+                    }
+
+                    self.write_buf0::<_, SLEEP>(stream).await?;
+
+                    if is_completed {
+                        self.state = State::Idle;
+                    }
                 }
             },
         }
@@ -250,7 +303,10 @@ where
         body_framing: BodyFraming,
     ) -> Result<(), IoError> {
         if self.state != State::Idle {
-            return Err(IoError::new(IoErrorKind::Other, "state should is Idle"));
+            return Err(IoError::other("state should is Idle"));
+        }
+        if body_framing == BodyFraming::CloseDelimited {
+            return Err(IoError::new(IoErrorKind::InvalidInput, "Response only"));
         }
 
         self.buf.clear();
@@ -261,13 +317,17 @@ where
 
         self.encode_head(parts)?;
 
-        self.write_head0::<_, SLEEP>(stream).await?;
+        self.write_buf0::<_, SLEEP>(stream).await?;
+
+        if body_framing != BodyFraming::Chunked {
+            self.trailers.clear();
+        }
 
         match body_framing {
             BodyFraming::Neither => {
                 self.state = State::Idle;
             }
-            BodyFraming::ContentLength(n) if n == 0 => {
+            BodyFraming::ContentLength(0) => {
                 self.state = State::Idle;
             }
             _ => {
@@ -293,6 +353,7 @@ pub type Http1ResponseEncoderInner =
     Http1Encoder<(ResponseParts, ReasonPhrase), ResponseHeadRenderer>;
 pub struct Http1ResponseEncoder {
     inner: Http1ResponseEncoderInner,
+    request_method: Option<Method>,
 }
 impl Deref for Http1ResponseEncoder {
     type Target = Http1ResponseEncoderInner;
@@ -310,8 +371,15 @@ impl Http1ResponseEncoder {
     pub fn new(buf_capacity: usize) -> Self {
         Self {
             inner: Http1ResponseEncoderInner::new(buf_capacity),
+            request_method: None,
         }
     }
+
+    /// The method of the request that the next response answers,
+    /// e.g. a response to HEAD has the headers of the body but not the body.
+    pub fn set_request_method(&mut self, method: Option<Method>) {
+        self.request_method = method;
+    }
 }
 
 #[async_trait]
@@ -327,7 +395,7 @@ where
         body_framing: BodyFraming,
     ) -> Result<(), IoError> {
         if self.state != State::Idle {
-            return Err(IoError::new(IoErrorKind::Other, "state should is Idle"));
+            return Err(IoError::other("state should is Idle"));
         }
 
         self.buf.clear();
@@ -336,16 +404,39 @@ where
         let (mut parts, _) = head.into_parts();
 
         self.update_headers(&mut parts.headers, &parts.version, &body_framing)?;
+        // Without it, the empty body would be delimited by the close of the connection.
+        if body_framing == BodyFraming::ContentLength(0)
+            && !parts.status.is_informational()
+            && parts.status != StatusCode::NO_CONTENT
+            && parts.status != StatusCode::NOT_MODIFIED
+        {
+            parts
+                .headers
+                .insert(CONTENT_LENGTH, HeaderValue::from_static("0"));
+        }
+
+        let is_head = self.request_method == Some(Method::HEAD);
+        // Interim responses answer the same request as the final one.
+        if !parts.status.is_informational() {
+            self.request_method = None;
+        }
 
         self.encode_head((parts, reason_phrase))?;
 
-        self.write_head0::<_, SLEEP>(stream).await?;
+        self.write_buf0::<_, SLEEP>(stream).await?;
+
+        if body_framing != BodyFraming::Chunked || is_head {
+            self.trailers.clear();
+        }
 
         match body_framing {
             BodyFraming::Neither => {
                 self.state = State::Idle;
             }
-            BodyFraming::ContentLength(n) if n == 0 => {
+            BodyFraming::ContentLength(0) => {
+                self.state = State::Idle;
+            }
+            _ if is_head => {
                 self.state = State::Idle;
             }
             _ => {
@@ -4,11 +4,23 @@ pub use http1_spec;
 
 //
 pub mod body;
+pub mod body_reader;
+pub mod client;
 pub mod decoder;
 pub mod encoder;
+pub mod pool;
+pub mod rewind;
+pub mod server;
 pub mod stream;
 
+#[cfg(feature = "bytes")]
+pub use body::DecoderBytesBody;
 pub use body::{DecoderBody, EncoderBody};
+pub use body_reader::Http1BodyReader;
+pub use client::{Http1Client, Http1ClientConfig};
 pub use decoder::{Http1RequestDecoder, Http1ResponseDecoder};
 pub use encoder::{Http1RequestEncoder, Http1ResponseEncoder};
+pub use pool::{Connector, Http1ClientPool, Http1ClientPoolConfig, Http1PooledConnection, Origin};
+pub use rewind::Rewind;
+pub use server::{Acceptor, Http1Server, Http1ServerConfig, Http1ServerShutdown, Service};
 pub use stream::{Http1ClientStream, Http1ServerStream};
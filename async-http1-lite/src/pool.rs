@@ -0,0 +1,443 @@
+use core::{
+    fmt,
+    future::poll_fn,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    time::Duration,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Instant,
+};
+
+use async_sleep::Sleepble;
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncWrite};
+use http::{uri::Scheme, Uri};
+
+use crate::stream::Http1ClientStream;
+
+//
+//
+//
+/// The key of the pool, the scheme, the host and the port of the request target.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Origin {
+    scheme: Scheme,
+    host: String,
+    port: u16,
+}
+impl Origin {
+    pub fn new(scheme: Scheme, host: impl AsRef<str>, port: u16) -> Self {
+        Self {
+            scheme,
+            host: host.as_ref().to_ascii_lowercase(),
+            port,
+        }
+    }
+
+    /// The port defaults to 80 for http and 443 for https.
+    pub fn from_uri(uri: &Uri) -> Result<Self, IoError> {
+        let scheme = uri
+            .scheme()
+            .ok_or_else(|| IoError::new(IoErrorKind::InvalidInput, "missing scheme"))?;
+        let host = uri
+            .host()
+            .ok_or_else(|| IoError::new(IoErrorKind::InvalidInput, "missing host"))?;
+        let port = match uri.port_u16() {
+            Some(port) => port,
+            None if scheme == &Scheme::HTTP => 80,
+            None if scheme == &Scheme::HTTPS => 443,
+            None => return Err(IoError::new(IoErrorKind::InvalidInput, "missing port")),
+        };
+
+        Ok(Self::new(scheme.to_owned(), host, port))
+    }
+
+    pub fn scheme(&self) -> &Scheme {
+        &self.scheme
+    }
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}:{}", self.scheme, self.host, self.port)
+    }
+}
+
+//
+//
+//
+/// Opens the connections of the pool, e.g. TCP, TLS over TCP or Unix sockets.
+#[async_trait]
+pub trait Connector {
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send;
+
+    async fn connect(&self, origin: &Origin) -> Result<Self::Stream, IoError>;
+}
+
+//
+//
+//
+#[derive(Debug, Clone)]
+pub struct Http1ClientPoolConfig {
+    /// Idle connections kept across all the origins.
+    pub max_idle: usize,
+    /// Connections opened to one origin, in use or idle, more acquirers wait.
+    pub max_per_host: usize,
+    /// Idle connections older than this are closed.
+    pub idle_timeout: Duration,
+}
+impl Default for Http1ClientPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_idle: 32,
+            max_per_host: 8,
+            idle_timeout: Duration::from_secs(90),
+        }
+    }
+}
+
+/// Keeps the idle `Http1ClientStream`s per origin.
+///
+/// Cheap to clone, the clones share the connections.
+pub struct Http1ClientPool<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    inner: Arc<Inner<C, SLEEP>>,
+}
+impl<C, SLEEP> Clone for Http1ClientPool<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+struct Inner<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    connector: C,
+    config: Http1ClientPoolConfig,
+    state: Mutex<State<C::Stream, SLEEP>>,
+}
+
+struct State<S, SLEEP>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    SLEEP: Sleepble,
+{
+    hosts: HashMap<Origin, Host<S, SLEEP>>,
+    idle_count: usize,
+}
+
+struct Host<S, SLEEP>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    SLEEP: Sleepble,
+{
+    // Most recently used at the back.
+    idle: VecDeque<Idle<S, SLEEP>>,
+    // In use, idle or connecting.
+    open_count: usize,
+    waiters: Vec<Waker>,
+}
+impl<S, SLEEP> Default for Host<S, SLEEP>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    SLEEP: Sleepble,
+{
+    fn default() -> Self {
+        Self {
+            idle: VecDeque::new(),
+            open_count: 0,
+            waiters: Vec::new(),
+        }
+    }
+}
+impl<S, SLEEP> Host<S, SLEEP>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    SLEEP: Sleepble,
+{
+    fn wake(&mut self) {
+        for waker in self.waiters.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+struct Idle<S, SLEEP>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    SLEEP: Sleepble,
+{
+    stream: Http1ClientStream<S, SLEEP>,
+    since: Instant,
+}
+
+impl<C, SLEEP> Http1ClientPool<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    pub fn new(connector: C) -> Self {
+        Self::with(connector, Default::default())
+    }
+    pub fn with(connector: C, config: Http1ClientPoolConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                connector,
+                config: Http1ClientPoolConfig {
+                    max_per_host: config.max_per_host.max(1),
+                    ..config
+                },
+                state: Mutex::new(State {
+                    hosts: HashMap::new(),
+                    idle_count: 0,
+                }),
+            }),
+        }
+    }
+
+    pub fn get_config(&self) -> &Http1ClientPoolConfig {
+        &self.inner.config
+    }
+    pub fn get_connector(&self) -> &C {
+        &self.inner.connector
+    }
+
+    /// Reuses an idle connection to the origin, or opens a new one.
+    ///
+    /// Waits while max_per_host connections to the origin are in use.
+    pub async fn acquire(
+        &self,
+        origin: &Origin,
+    ) -> Result<Http1PooledConnection<C, SLEEP>, IoError> {
+        let idle = poll_fn(|cx| self.inner.poll_acquire(origin, cx)).await;
+
+        let mut conn = Http1PooledConnection {
+            pool: self.inner.clone(),
+            origin: origin.to_owned(),
+            is_reused: idle.is_some(),
+            stream: idle,
+        };
+
+        if conn.stream.is_none() {
+            // The slot is released by the drop of conn if failed or cancelled.
+            let stream = self.inner.connector.connect(origin).await?;
+            conn.stream = Some(Http1ClientStream::new(stream));
+        }
+
+        Ok(conn)
+    }
+
+    /// Closes the idle connections older than idle_timeout, they are also evicted on acquire.
+    pub fn evict_expired(&self) {
+        let mut state = self.inner.lock();
+        let idle_timeout = self.inner.config.idle_timeout;
+        let mut evicted = 0;
+        for host in state.hosts.values_mut() {
+            let n = host.idle.len();
+            host.idle.retain(|x| x.since.elapsed() < idle_timeout);
+            let n = n - host.idle.len();
+            if n > 0 {
+                host.open_count -= n;
+                host.wake();
+                evicted += n;
+            }
+        }
+        state.idle_count -= evicted;
+        state
+            .hosts
+            .retain(|_, x| x.open_count > 0 || !x.waiters.is_empty());
+    }
+
+    pub fn idle_count(&self) -> usize {
+        self.inner.lock().idle_count
+    }
+    pub fn open_count(&self, origin: &Origin) -> usize {
+        self.inner
+            .lock()
+            .hosts
+            .get(origin)
+            .map(|x| x.open_count)
+            .unwrap_or(0)
+    }
+}
+
+impl<C, SLEEP> Inner<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    fn lock(&self) -> MutexGuard<'_, State<C::Stream, SLEEP>> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    // Ready(Some) with an idle connection, Ready(None) with a slot to connect.
+    fn poll_acquire(
+        &self,
+        origin: &Origin,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Http1ClientStream<C::Stream, SLEEP>>> {
+        let mut state = self.lock();
+        let state = &mut *state;
+        let host = state.hosts.entry(origin.to_owned()).or_default();
+
+        while let Some(mut idle) = host.idle.pop_back() {
+            state.idle_count -= 1;
+
+            if idle.since.elapsed() < self.config.idle_timeout && is_healthy(&mut idle.stream) {
+                return Poll::Ready(Some(idle.stream));
+            }
+            host.open_count -= 1;
+        }
+
+        if host.open_count < self.config.max_per_host {
+            host.open_count += 1;
+            return Poll::Ready(None);
+        }
+
+        host.waiters.push(cx.waker().to_owned());
+        Poll::Pending
+    }
+
+    fn release(&self, origin: &Origin, stream: Option<Http1ClientStream<C::Stream, SLEEP>>) {
+        let mut state = self.lock();
+        let state = &mut *state;
+        let Some(host) = state.hosts.get_mut(origin) else {
+            return;
+        };
+
+        match stream {
+            Some(stream)
+                if stream.can_reuse()
+                    && stream.is_idle()
+                    && state.idle_count < self.config.max_idle =>
+            {
+                host.idle.push_back(Idle {
+                    stream,
+                    since: Instant::now(),
+                });
+                state.idle_count += 1;
+            }
+            _ => {
+                host.open_count -= 1;
+            }
+        }
+        host.wake();
+
+        if host.open_count == 0 && host.waiters.is_empty() {
+            state.hosts.remove(origin);
+        }
+    }
+}
+
+// An idle connection has nothing to read, EOF means the server closed it.
+fn is_healthy<S, SLEEP>(stream: &mut Http1ClientStream<S, SLEEP>) -> bool
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    SLEEP: Sleepble,
+{
+    if stream.has_unparsed_bytes() {
+        return false;
+    }
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut buf = [0; 1];
+    matches!(
+        Pin::new(stream.get_mut()).poll_read(&mut cx, &mut buf),
+        Poll::Pending
+    )
+}
+
+// Waker::noop requires Rust 1.85.
+pub(crate) fn noop_waker() -> Waker {
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| RAW, |_| {}, |_| {}, |_| {});
+    const RAW: RawWaker = RawWaker::new(core::ptr::null(), &VTABLE);
+
+    // Safety: the functions of the vtable do nothing with the data pointer.
+    unsafe { Waker::from_raw(RAW) }
+}
+
+//
+//
+//
+/// Goes back to the pool on drop, if the exchange is completed and the connection is persistent.
+pub struct Http1PooledConnection<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    pool: Arc<Inner<C, SLEEP>>,
+    origin: Origin,
+    is_reused: bool,
+    stream: Option<Http1ClientStream<C::Stream, SLEEP>>,
+}
+impl<C, SLEEP> Deref for Http1PooledConnection<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    type Target = Http1ClientStream<C::Stream, SLEEP>;
+
+    fn deref(&self) -> &Http1ClientStream<C::Stream, SLEEP> {
+        self.stream.as_ref().expect("Never")
+    }
+}
+impl<C, SLEEP> DerefMut for Http1PooledConnection<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    fn deref_mut(&mut self) -> &mut Http1ClientStream<C::Stream, SLEEP> {
+        self.stream.as_mut().expect("Never")
+    }
+}
+impl<C, SLEEP> Http1PooledConnection<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+    /// Whether taken from the idle connections, the server may have closed it meanwhile.
+    pub fn is_reused(&self) -> bool {
+        self.is_reused
+    }
+
+    /// Takes the connection out of the pool, e.g. for an upgrade.
+    pub fn detach(mut self) -> Http1ClientStream<C::Stream, SLEEP> {
+        // The drop releases the slot only.
+        self.stream.take().expect("Never")
+    }
+}
+impl<C, SLEEP> Drop for Http1PooledConnection<C, SLEEP>
+where
+    C: Connector,
+    SLEEP: Sleepble,
+{
+    fn drop(&mut self) {
+        let stream = self.stream.take();
+        self.pool.release(&self.origin, stream);
+    }
+}
@@ -0,0 +1,87 @@
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::io::Error as IoError;
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+//
+//
+//
+/// The stream taken back after an upgrade or a CONNECT tunnel.
+///
+/// Reads the bytes already read by the decoder first, e.g. the TLS ServerHello right after
+/// the response of CONNECT, then the stream.
+pub struct Rewind<S> {
+    stream: S,
+    leftover: Vec<u8>,
+    offset: usize,
+}
+impl<S> Rewind<S> {
+    pub fn new(stream: S, leftover: Vec<u8>) -> Self {
+        Self {
+            stream,
+            leftover,
+            offset: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+    /// The leftover bytes not read yet.
+    pub fn get_leftover(&self) -> &[u8] {
+        &self.leftover[self.offset..]
+    }
+
+    /// Returns the stream with the leftover bytes not read yet.
+    pub fn into_parts(mut self) -> (S, Vec<u8>) {
+        self.leftover.drain(..self.offset);
+        (self.stream, self.leftover)
+    }
+}
+
+impl<S> AsyncRead for Rewind<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        if this.offset < this.leftover.len() {
+            let n = buf.len().min(this.leftover.len() - this.offset);
+            buf[..n].copy_from_slice(&this.leftover[this.offset..this.offset + n]);
+            this.offset += n;
+            return Poll::Ready(Ok(n));
+        }
+        Pin::new(&mut this.stream).poll_read(cx, buf)
+    }
+}
+
+impl<S> AsyncWrite for Rewind<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx)
+    }
+}
@@ -0,0 +1,475 @@
+use core::{
+    future::{poll_fn, Future},
+    marker::PhantomData,
+    pin::Pin,
+    task::{Poll, Waker},
+    time::Duration,
+};
+use std::{
+    collections::HashMap,
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_sleep::Sleepble;
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncWrite};
+use http::{header::CONNECTION, HeaderValue, Request, Response, StatusCode};
+use http1_spec::{connection::CLOSE, head_parser::HeadParseError};
+
+use crate::{decoder::DecodeError, stream::Http1ServerStream};
+
+//
+//
+//
+/// Handles one request, an error is answered with `500 Internal Server Error`.
+#[async_trait]
+pub trait Service {
+    async fn call(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, IoError>;
+}
+
+#[async_trait]
+impl<F, Fut> Service for F
+where
+    F: Fn(Request<Vec<u8>>) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Response<Vec<u8>>, IoError>> + Send,
+{
+    async fn call(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, IoError> {
+        self(request).await
+    }
+}
+
+/// Accepts the connections of the server, e.g. a TCP or Unix listener.
+#[async_trait]
+pub trait Acceptor {
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    async fn accept(&self) -> Result<Self::Stream, IoError>;
+}
+
+pub type ConnectionFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+//
+//
+//
+/// Stops the accept loop and closes the connections after their in-flight requests.
+#[derive(Clone, Default)]
+pub struct Http1ServerShutdown {
+    inner: Arc<Mutex<ShutdownState>>,
+}
+#[derive(Default)]
+struct ShutdownState {
+    is_shutdown: bool,
+    connection_count: usize,
+    // One slot per pending wait, keyed by its id.
+    waiters: HashMap<usize, Waker>,
+    next_waiter_id: usize,
+}
+impl Http1ServerShutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn shutdown(&self) {
+        let mut state = self.lock();
+        state.is_shutdown = true;
+        wake_all(&mut state);
+    }
+    pub fn is_shutdown(&self) -> bool {
+        self.lock().is_shutdown
+    }
+    pub fn connection_count(&self) -> usize {
+        self.lock().connection_count
+    }
+
+    /// Until shutdown is called.
+    pub async fn wait(&self) {
+        self.wait_until(|state| state.is_shutdown).await
+    }
+    /// Until shutdown is called and all the connections are closed.
+    pub async fn wait_idle(&self) {
+        self.wait_until(|state| state.is_shutdown && state.connection_count == 0)
+            .await
+    }
+
+    async fn wait_until(&self, f: impl Fn(&ShutdownState) -> bool) {
+        let waiter = self.waiter();
+
+        poll_fn(|cx| {
+            let mut state = self.lock();
+            if f(&state) {
+                Poll::Ready(())
+            } else {
+                // Replaces the waker of the previous poll.
+                state.waiters.insert(waiter.id, cx.waker().to_owned());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    fn waiter(&self) -> Waiter<'_> {
+        let mut state = self.lock();
+        let id = state.next_waiter_id;
+        state.next_waiter_id = state.next_waiter_id.wrapping_add(1);
+        Waiter { shutdown: self, id }
+    }
+
+    fn track(&self) -> ConnectionGuard {
+        self.lock().connection_count += 1;
+        ConnectionGuard(self.to_owned())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ShutdownState> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+struct ConnectionGuard(Http1ServerShutdown);
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.connection_count -= 1;
+        // Only wait_idle cares about the connections, and only the last one.
+        if state.connection_count == 0 {
+            wake_all(&mut state);
+        }
+    }
+}
+
+// Deregisters the waker when the wait completes or is dropped.
+struct Waiter<'a> {
+    shutdown: &'a Http1ServerShutdown,
+    id: usize,
+}
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        self.shutdown.lock().waiters.remove(&self.id);
+    }
+}
+
+fn wake_all(state: &mut ShutdownState) {
+    for (_, waker) in state.waiters.drain() {
+        waker.wake();
+    }
+}
+
+//
+//
+//
+#[derive(Debug, Clone)]
+pub struct Http1ServerConfig {
+    /// Requests already received, e.g. in the same segment, handled concurrently.
+    /// The responses are always written in request order.
+    pub pipeline_depth: usize,
+    /// Per read of the stream, also how long an idle connection is kept alive.
+    pub read_timeout: Option<Duration>,
+    /// Per write of the stream.
+    pub write_timeout: Option<Duration>,
+}
+impl Default for Http1ServerConfig {
+    fn default() -> Self {
+        Self {
+            pipeline_depth: 1,
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+}
+
+/// Drives the connections through the Service.
+///
+/// Cheap to clone, the clones share the service and the shutdown.
+pub struct Http1Server<SV, SLEEP>
+where
+    SV: Service,
+    SLEEP: Sleepble,
+{
+    service: Arc<SV>,
+    config: Http1ServerConfig,
+    shutdown: Http1ServerShutdown,
+    phantom: PhantomData<fn() -> SLEEP>,
+}
+impl<SV, SLEEP> Clone for Http1Server<SV, SLEEP>
+where
+    SV: Service,
+    SLEEP: Sleepble,
+{
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            config: self.config.clone(),
+            shutdown: self.shutdown.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+impl<SV, SLEEP> Http1Server<SV, SLEEP>
+where
+    SV: Service + Send + Sync + 'static,
+    SLEEP: Sleepble + Send + 'static,
+{
+    pub fn new(service: SV) -> Self {
+        Self::with(service, Default::default())
+    }
+    pub fn with(service: SV, config: Http1ServerConfig) -> Self {
+        Self {
+            service: Arc::new(service),
+            config: Http1ServerConfig {
+                pipeline_depth: config.pipeline_depth.max(1),
+                ..config
+            },
+            shutdown: Http1ServerShutdown::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn get_config(&self) -> &Http1ServerConfig {
+        &self.config
+    }
+    pub fn get_shutdown(&self) -> &Http1ServerShutdown {
+        &self.shutdown
+    }
+
+    /// Accepts until shutdown, then waits for the connections to be closed.
+    ///
+    /// A failed accept does not stop the server, e.g. too many open files, it is retried after a delay.
+    ///
+    /// `spawn` runs each connection, e.g. with `tokio::spawn` or `async_global_executor::spawn`.
+    pub async fn serve<A>(
+        &self,
+        acceptor: A,
+        spawn: impl Fn(ConnectionFuture),
+    ) -> Result<(), IoError>
+    where
+        A: Acceptor,
+    {
+        loop {
+            let stream = match select(Box::pin(acceptor.accept()), Box::pin(self.shutdown.wait()))
+                .await
+            {
+                Either::Left(Ok(stream)) => stream,
+                Either::Left(Err(_)) => {
+                    let delay = Box::pin(async_sleep::sleep::sleep::<SLEEP>(ACCEPT_ERROR_DELAY));
+                    match select(delay, Box::pin(self.shutdown.wait())).await {
+                        Either::Left(()) => continue,
+                        Either::Right(()) => break,
+                    }
+                }
+                Either::Right(()) => break,
+            };
+
+            // Counted before spawn, so wait_idle also waits for the connections not started yet.
+            let guard = self.shutdown.track();
+            let server = self.clone();
+            spawn(Box::pin(async move {
+                let _ = server.serve_connection0(stream, guard).await;
+            }));
+        }
+
+        self.shutdown.wait_idle().await;
+
+        Ok(())
+    }
+
+    /// Reads the requests and writes the responses until the connection is not persistent,
+    /// closed by the client, or shutdown.
+    pub async fn serve_connection<S>(&self, stream: S) -> Result<(), IoError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        self.serve_connection0(stream, self.shutdown.track()).await
+    }
+
+    async fn serve_connection0<S>(&self, stream: S, _guard: ConnectionGuard) -> Result<(), IoError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let mut stream: Http1ServerStream<S, SLEEP> = Http1ServerStream::new(stream);
+        stream.set_pipeline_depth(self.config.pipeline_depth);
+        if let Some(dur) = self.config.read_timeout {
+            stream.set_read_timeout(dur);
+        }
+        if let Some(dur) = self.config.write_timeout {
+            stream.set_write_timeout(dur);
+        }
+
+        loop {
+            // An idle connection is closed at shutdown, a request already arriving is answered.
+            let first = match select(
+                Box::pin(stream.wait_request()),
+                Box::pin(self.shutdown.wait()),
+            )
+            .await
+            {
+                Either::Left(Ok(true)) => stream.read_request().await,
+                Either::Left(Ok(false)) => return Ok(()),
+                Either::Left(Err(err)) => Err(err),
+                Either::Right(()) => return Ok(()),
+            };
+
+            let mut requests = vec![];
+            let mut read_err = None;
+            match first {
+                Ok(request) => requests.push(request),
+                Err(err) => read_err = Some(err),
+            }
+            while read_err.is_none()
+                && requests.len() < self.config.pipeline_depth
+                && stream.can_reuse()
+                && stream.has_buffered_request()
+            {
+                match stream.read_request().await {
+                    Ok(request) => requests.push(request),
+                    Err(err) => read_err = Some(err),
+                }
+            }
+
+            let responses = join_all(
+                requests
+                    .into_iter()
+                    .map(|request| self.service.call(request))
+                    .collect(),
+            )
+            .await;
+
+            let n = responses.len();
+            // A response to HEAD is written with the Content-Length of its body, but not the body.
+            for (i, response) in responses.into_iter().enumerate() {
+                let mut response =
+                    response.unwrap_or_else(|_| error_response(StatusCode::INTERNAL_SERVER_ERROR));
+                if i == n - 1 && read_err.is_none() && self.shutdown.is_shutdown() {
+                    response
+                        .headers_mut()
+                        .insert(CONNECTION, HeaderValue::from_static(CLOSE));
+                }
+
+                stream.write_response(response, None).await?;
+            }
+
+            if let Some(err) = read_err {
+                if let Some(status) = error_status(&err) {
+                    // Whether the failed request is still waiting for a response.
+                    if stream.get_pipelined_count() > 0 {
+                        let mut response = error_response(status);
+                        response
+                            .headers_mut()
+                            .insert(CONNECTION, HeaderValue::from_static(CLOSE));
+                        stream.write_response(response, None).await?;
+                    }
+                }
+                return match err.kind() {
+                    IoErrorKind::UnexpectedEof | IoErrorKind::TimedOut => Ok(()),
+                    _ => Err(err),
+                };
+            }
+
+            if !stream.can_reuse() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// None if nothing could be answered, e.g. the client closed the connection.
+fn error_status(err: &IoError) -> Option<StatusCode> {
+    if err.kind() != IoErrorKind::InvalidInput {
+        return None;
+    }
+    if let Some(err) = err.get_ref().and_then(|x| x.downcast_ref::<DecodeError>()) {
+        return match err {
+            DecodeError::HeadTooLarge => Some(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE),
+            DecodeError::BodyTooLarge => Some(StatusCode::PAYLOAD_TOO_LARGE),
+        };
+    }
+    match err
+        .get_ref()
+        .and_then(|x| x.downcast_ref::<HeadParseError>())
+    {
+        Some(
+            HeadParseError::TooLongHeader
+            | HeadParseError::TooLongHeaders
+            | HeadParseError::TooManyHeaders,
+        ) => Some(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE),
+        _ => Some(StatusCode::BAD_REQUEST),
+    }
+}
+
+fn error_response(status: StatusCode) -> Response<Vec<u8>> {
+    let mut response = Response::new(vec![]);
+    *response.status_mut() = status;
+    response
+}
+
+//
+//
+//
+enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+// Polls both, the left first.
+async fn select<L, R>(mut left: L, mut right: R) -> Either<L::Output, R::Output>
+where
+    L: Future + Unpin,
+    R: Future + Unpin,
+{
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = Pin::new(&mut left).poll(cx) {
+            return Poll::Ready(Either::Left(output));
+        }
+        if let Poll::Ready(output) = Pin::new(&mut right).poll(cx) {
+            return Poll::Ready(Either::Right(output));
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+async fn join_all<T>(mut futures: Vec<Pin<Box<dyn Future<Output = T> + Send + '_>>>) -> Vec<T> {
+    let mut outputs = futures.iter().map(|_| None).collect::<Vec<_>>();
+    poll_fn(|cx| {
+        let mut is_pending = false;
+        for (future, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            if output.is_none() {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(x) => *output = Some(x),
+                    Poll::Pending => is_pending = true,
+                }
+            }
+        }
+        if is_pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    })
+    .await;
+    outputs.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::task::Context;
+
+    #[test]
+    fn wait_with_one_waker() {
+        let shutdown = Http1ServerShutdown::new();
+        let waker = crate::pool::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut wait = Box::pin(shutdown.wait());
+        for _ in 0..3 {
+            assert!(wait.as_mut().poll(&mut cx).is_pending());
+        }
+        assert_eq!(shutdown.lock().waiters.len(), 1);
+
+        drop(wait);
+        assert!(shutdown.lock().waiters.is_empty());
+    }
+}
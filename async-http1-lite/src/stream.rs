@@ -1,22 +1,40 @@
 use core::{
+    cmp::min,
+    future::poll_fn,
     marker::PhantomData,
     ops::{Deref, DerefMut},
     pin::Pin,
     task::{Context, Poll},
     time::Duration,
 };
-use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::{
+    collections::VecDeque,
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+};
 
 use async_sleep::Sleepble;
 use async_trait::async_trait;
 use futures_io::{AsyncRead, AsyncWrite};
-use http::{Request, Response};
-use http1_spec::{body_framing::BodyFraming, head_renderer::Head, ReasonPhrase};
+use http::{
+    header::{CONNECTION, EXPECT, HOST, PROXY_AUTHORIZATION, UPGRADE},
+    uri::Authority,
+    HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Version,
+};
+use http1_spec::{
+    body_framing::BodyFraming,
+    connection::{is_persistent, is_upgrade, CLOSE, UPGRADE as CONNECTION_UPGRADE},
+    head_renderer::Head,
+    ReasonPhrase,
+};
 
+#[cfg(feature = "bytes")]
+use crate::body::DecoderBytesBody;
 use crate::{
     body::{DecoderBody, EncoderBody},
+    body_reader::Http1BodyReader,
     decoder::{Http1RequestDecoder, Http1ResponseDecoder},
     encoder::{Http1RequestEncoder, Http1ResponseEncoder},
+    rewind::Rewind,
 };
 
 //
@@ -31,6 +49,9 @@ where
 {
     async fn read_head(&mut self, stream: &mut S) -> Result<(H, BodyFraming), IoError>;
     async fn read_body(&mut self, stream: &mut S) -> Result<DecoderBody, IoError>;
+    /// Same as read_body, but the bytes share the read buf instead of being copied.
+    #[cfg(feature = "bytes")]
+    async fn read_body_bytes(&mut self, stream: &mut S) -> Result<DecoderBytesBody, IoError>;
 
     fn set_read_timeout(&mut self, dur: Duration);
 }
@@ -53,6 +74,27 @@ where
     fn set_write_timeout(&mut self, dur: Duration);
 }
 
+const BODY_READER_BUF_LEN: usize = 8 * 1024;
+const CONTINUE: &str = "100-continue";
+const EARLY_HINTS: u16 = 103;
+
+/// Called with the status and headers of each interim response, except `101 Switching Protocols`.
+pub type InterimResponseCallback = Box<dyn FnMut(StatusCode, &HeaderMap<HeaderValue>) + Send>;
+
+fn is_expect_continue(headers: &HeaderMap<HeaderValue>) -> bool {
+    headers
+        .get(EXPECT)
+        .map(|x| x.as_bytes().eq_ignore_ascii_case(CONTINUE.as_bytes()))
+        .unwrap_or(false)
+}
+
+fn pipeline_aborted() -> IoError {
+    IoError::new(
+        IoErrorKind::ConnectionAborted,
+        "connection closed before the response",
+    )
+}
+
 //
 //
 //
@@ -108,6 +150,52 @@ where
         self.encoder.write_body(&mut self.stream, body).await
     }
 
+    /// Copies the body of the written head from the reader.
+    ///
+    /// With a content_length, exactly that many bytes are copied, otherwise until EOF of the reader.
+    pub async fn write_body_from_reader<R: AsyncRead + Unpin>(
+        &mut self,
+        reader: &mut R,
+        content_length: Option<usize>,
+    ) -> Result<(), IoError> {
+        let mut buf = vec![0u8; BODY_READER_BUF_LEN];
+        let mut n_remaining = content_length;
+        loop {
+            let buf_len = min(buf.len(), n_remaining.unwrap_or(usize::MAX));
+            let n = poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut buf[..buf_len])).await?;
+
+            match n_remaining.as_mut() {
+                Some(n_remaining) => {
+                    if n == 0 {
+                        return Err(IoError::new(
+                            IoErrorKind::UnexpectedEof,
+                            "body reader is shorter than content_length",
+                        ));
+                    }
+                    *n_remaining -= n;
+
+                    if *n_remaining == 0 {
+                        self.write_body(EncoderBody::Completed(buf[..n].to_vec()))
+                            .await?;
+                        break;
+                    }
+                    self.write_body(EncoderBody::Partial(buf[..n].to_vec()))
+                        .await?;
+                }
+                None => {
+                    if n == 0 {
+                        self.write_body(EncoderBody::Completed(Vec::new())).await?;
+                        break;
+                    }
+                    self.write_body(EncoderBody::Partial(buf[..n].to_vec()))
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     //
     pub async fn read_head(&mut self) -> Result<(DH, BodyFraming), IoError> {
         self.decoder.read_head(&mut self.stream).await
@@ -115,6 +203,37 @@ where
     pub async fn read_body(&mut self) -> Result<DecoderBody, IoError> {
         self.decoder.read_body(&mut self.stream).await
     }
+    async fn read_body_to_end(&mut self, body_framing: &BodyFraming) -> Result<Vec<u8>, IoError> {
+        let mut body = Vec::new();
+        match body_framing {
+            BodyFraming::Neither => {}
+            BodyFraming::ContentLength(0) => {}
+            _ => loop {
+                match self.read_body().await? {
+                    DecoderBody::Completed(bytes) => {
+                        body.extend_from_slice(&bytes);
+                        break;
+                    }
+                    DecoderBody::Partial(bytes) => {
+                        body.extend_from_slice(&bytes);
+                    }
+                }
+            },
+        }
+        Ok(body)
+    }
+
+    pub fn body_reader(&mut self) -> Http1BodyReader<'_, S, SLEEP, D, DH>
+    where
+        S: Send,
+        D: Send,
+    {
+        Http1BodyReader::new(&mut self.decoder, &mut self.stream)
+    }
+    #[cfg(feature = "bytes")]
+    pub async fn read_body_bytes(&mut self) -> Result<DecoderBytesBody, IoError> {
+        self.decoder.read_body_bytes(&mut self.stream).await
+    }
 }
 
 //
@@ -128,12 +247,18 @@ pub type Http1ClientStreamInner<S, SLEEP> = Http1Stream<
     Http1RequestEncoder,
     Request<()>,
 >;
+pub type Http1ClientBodyReader<'a, S, SLEEP> =
+    Http1BodyReader<'a, S, SLEEP, Http1ResponseDecoder, (Response<()>, ReasonPhrase)>;
 pub struct Http1ClientStream<S, SLEEP>
 where
     S: AsyncRead + AsyncWrite + Unpin + Send,
     SLEEP: Sleepble,
 {
     inner: Http1ClientStreamInner<S, SLEEP>,
+    keep_alive: bool,
+    interim_response_callback: Option<InterimResponseCallback>,
+    // Methods of the requests written but not answered yet, in request order.
+    pending_methods: VecDeque<Method>,
 }
 impl<S, SLEEP> Deref for Http1ClientStream<S, SLEEP>
 where
@@ -170,6 +295,9 @@ where
     pub fn with(stream: S, decoder: Http1ResponseDecoder, encoder: Http1RequestEncoder) -> Self {
         Self {
             inner: Http1ClientStreamInner::new(stream, decoder, encoder),
+            keep_alive: true,
+            interim_response_callback: None,
+            pending_methods: VecDeque::new(),
         }
     }
 
@@ -179,12 +307,93 @@ where
     pub fn get_mut(&mut self) -> &mut S {
         &mut self.inner.stream
     }
+    pub fn get_read_trailers(&self) -> &HeaderMap<HeaderValue> {
+        self.decoder.get_trailers()
+    }
+    pub fn set_write_trailers(&mut self, trailers: HeaderMap<HeaderValue>) {
+        self.encoder.set_trailers(trailers)
+    }
+    pub fn set_interim_response_callback(&mut self, callback: Option<InterimResponseCallback>) {
+        self.interim_response_callback = callback;
+    }
+
     pub fn into_inner(self) -> Result<S, IoError> {
         if self.decoder.has_unparsed_bytes() {
-            return Err(IoError::new(IoErrorKind::Other, "has unparsed bytes"));
+            return Err(IoError::other("has unparsed bytes"));
         }
         Ok(self.inner.stream)
     }
+    /// Returns the stream with the bytes already read from it but not parsed,
+    /// e.g. the TLS ServerHello right after the response of CONNECT.
+    pub fn into_parts(mut self) -> (S, Vec<u8>) {
+        let leftover = self.decoder.take_unparsed_bytes();
+        (self.inner.stream, leftover)
+    }
+
+    /// Whether another request may be written after the current exchange.
+    pub fn can_reuse(&self) -> bool {
+        self.keep_alive
+    }
+    /// Whether bytes were read but not belong to any response yet.
+    pub fn has_unparsed_bytes(&self) -> bool {
+        self.decoder.has_unparsed_bytes()
+    }
+    /// Whether all the written requests are answered and the messages are fully read and written.
+    pub fn is_idle(&self) -> bool {
+        self.pending_methods.is_empty() && self.decoder.is_idle() && self.encoder.is_idle()
+    }
+
+    pub async fn write_head(
+        &mut self,
+        head: Request<()>,
+        body_framing: BodyFraming,
+    ) -> Result<(), IoError> {
+        if !self.keep_alive {
+            return Err(IoError::new(
+                IoErrorKind::NotConnected,
+                "connection is marked close",
+            ));
+        }
+
+        let method = head.method().to_owned();
+        let keep_alive = is_persistent(head.headers(), &head.version());
+
+        self.inner.write_head(head, body_framing).await?;
+
+        self.pending_methods.push_back(method);
+        self.keep_alive = keep_alive;
+
+        Ok(())
+    }
+
+    pub async fn read_head(
+        &mut self,
+    ) -> Result<((Response<()>, ReasonPhrase), BodyFraming), IoError> {
+        // The response answers the oldest request.
+        let method = self.pending_methods.front().cloned();
+        self.decoder.set_request_method(method);
+
+        let ((response, reason_phrase), body_framing) = self.inner.read_head().await?;
+
+        if !response.status().is_informational()
+            || response.status() == StatusCode::SWITCHING_PROTOCOLS
+        {
+            self.pending_methods.pop_front();
+        }
+
+        // Interim responses say nothing about the connection.
+        if !response.status().is_informational() {
+            self.keep_alive = self.keep_alive
+                && is_persistent(response.headers(), &response.version())
+                && body_framing != BodyFraming::CloseDelimited;
+        } else if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+            if let Some(callback) = self.interim_response_callback.as_mut() {
+                callback(response.status(), response.headers());
+            }
+        }
+
+        Ok(((response, reason_phrase), body_framing))
+    }
 
     pub async fn write_request(&mut self, request: Request<Vec<u8>>) -> Result<(), IoError> {
         let (parts, body) = request.into_parts();
@@ -195,7 +404,7 @@ where
         self.write_head(head, body_framing.clone()).await?;
         match body_framing {
             BodyFraming::Neither => {}
-            BodyFraming::ContentLength(n) if n == 0 => {}
+            BodyFraming::ContentLength(0) => {}
             _ => {
                 self.write_body(EncoderBody::Completed(body)).await?;
             }
@@ -204,31 +413,265 @@ where
         Ok(())
     }
 
-    pub async fn read_response(&mut self) -> Result<(Response<Vec<u8>>, ReasonPhrase), IoError> {
-        let ((response, reason_phrase), body_framing) = self.read_head().await?;
+    /// Without a content_length, the body is sent chunked, so the request must be HTTP/1.1.
+    pub async fn write_request_with_body_reader<R: AsyncRead + Unpin>(
+        &mut self,
+        request: Request<R>,
+        content_length: Option<usize>,
+    ) -> Result<(), IoError> {
+        let (parts, mut body) = request.into_parts();
+        let head = Request::from_parts(parts, ());
 
-        let mut body = Vec::new();
+        let body_framing = match content_length {
+            Some(n) => BodyFraming::ContentLength(n),
+            None if head.version() != Version::HTTP_11 => {
+                return Err(IoError::new(
+                    IoErrorKind::InvalidInput,
+                    "content_length is required, chunked is only valid in HTTP/1.1",
+                ));
+            }
+            None => BodyFraming::Chunked,
+        };
+
+        self.write_head(head, body_framing.clone()).await?;
         match body_framing {
-            BodyFraming::Neither => {}
-            BodyFraming::ContentLength(n) if n == 0 => {}
-            _ => loop {
-                match self.read_body().await? {
-                    DecoderBody::Completed(bytes) => {
-                        body.extend_from_slice(&bytes);
-                        break;
-                    }
-                    DecoderBody::Partial(bytes) => {
-                        body.extend_from_slice(&bytes);
-                    }
+            BodyFraming::ContentLength(0) => {}
+            _ => {
+                self.write_body_from_reader(&mut body, content_length)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends the body only after `100 Continue`, or after the timeout without any response.
+    ///
+    /// Returns the final response if the server answers without `100 Continue`, e.g. 417,
+    /// then the body is not sent and the connection can not be reused.
+    pub async fn write_request_with_expect_continue(
+        &mut self,
+        request: Request<Vec<u8>>,
+        timeout: Duration,
+    ) -> Result<Option<(Response<Vec<u8>>, ReasonPhrase)>, IoError> {
+        let (mut parts, body) = request.into_parts();
+        if body.is_empty() {
+            self.write_request(Request::from_parts(parts, body)).await?;
+            return Ok(None);
+        }
+
+        parts
+            .headers
+            .insert(EXPECT, HeaderValue::from_static(CONTINUE));
+        let head = Request::from_parts(parts, ());
+
+        self.write_head(head, BodyFraming::ContentLength(body.len()))
+            .await?;
+
+        loop {
+            match async_sleep::timeout::timeout::<SLEEP, _>(timeout, Box::pin(self.read_head()))
+                .await
+            {
+                Ok(Ok(((response, _), _))) if response.status() == StatusCode::CONTINUE => break,
+                Ok(Ok(((response, _), _)))
+                    if response.status().is_informational()
+                        && response.status() != StatusCode::SWITCHING_PROTOCOLS =>
+                {
+                    continue
                 }
-            },
+                Ok(Ok(((response, reason_phrase), body_framing))) => {
+                    self.keep_alive = false;
+
+                    let body = self.read_body_to_end(&body_framing).await?;
+                    let (parts, _) = response.into_parts();
+                    return Ok(Some((Response::from_parts(parts, body), reason_phrase)));
+                }
+                Ok(Err(err)) => return Err(err),
+                Err(_) => break,
+            }
         }
 
+        self.write_body(EncoderBody::Completed(body)).await?;
+
+        Ok(None)
+    }
+
+    /// Skips interim responses, except `101 Switching Protocols`, see set_interim_response_callback.
+    async fn read_final_head(
+        &mut self,
+    ) -> Result<((Response<()>, ReasonPhrase), BodyFraming), IoError> {
+        loop {
+            let ((response, reason_phrase), body_framing) = self.read_head().await?;
+            if response.status().is_informational()
+                && response.status() != StatusCode::SWITCHING_PROTOCOLS
+            {
+                continue;
+            }
+            break Ok(((response, reason_phrase), body_framing));
+        }
+    }
+
+    pub async fn read_response(&mut self) -> Result<(Response<Vec<u8>>, ReasonPhrase), IoError> {
+        let ((response, reason_phrase), body_framing) = self.read_final_head().await?;
+
+        let body = self.read_body_to_end(&body_framing).await?;
+
         let (parts, _) = response.into_parts();
         let response = Response::from_parts(parts, body);
 
         Ok((response, reason_phrase))
     }
+
+    /// Writes the requests back-to-back, then reads the responses in the same order.
+    ///
+    /// Returns one result per request. If the server closes the connection mid-pipeline,
+    /// the requests without response get `ConnectionAborted`, they may be retried on another connection.
+    /// Only for the servers known to support pipelining, see RFC 9112 section 9.3.2.
+    pub async fn send_pipelined(
+        &mut self,
+        requests: Vec<Request<Vec<u8>>>,
+    ) -> Vec<Result<(Response<Vec<u8>>, ReasonPhrase), IoError>> {
+        let n = requests.len();
+        let mut results = Vec::with_capacity(n);
+
+        let mut write_err = None;
+        let mut written = 0;
+        for request in requests {
+            if let Err(err) = self.write_request(request).await {
+                write_err = Some(err);
+                break;
+            }
+            written += 1;
+        }
+
+        let mut is_aborted = false;
+        for _ in 0..written {
+            if is_aborted {
+                results.push(Err(pipeline_aborted()));
+                continue;
+            }
+
+            match self.read_response().await {
+                Ok((response, reason_phrase)) => {
+                    // Server will not answer the rest.
+                    if !is_persistent(response.headers(), &response.version()) {
+                        is_aborted = true;
+                    }
+                    results.push(Ok((response, reason_phrase)));
+                }
+                Err(err) => {
+                    is_aborted = true;
+                    results.push(Err(match err.kind() {
+                        IoErrorKind::UnexpectedEof
+                        | IoErrorKind::ConnectionReset
+                        | IoErrorKind::BrokenPipe => pipeline_aborted(),
+                        _ => err,
+                    }));
+                }
+            }
+        }
+
+        if let Some(err) = write_err {
+            results.push(Err(err));
+        }
+        while results.len() < n {
+            results.push(Err(pipeline_aborted()));
+        }
+
+        if is_aborted || results.len() > written {
+            self.keep_alive = false;
+        }
+
+        results
+    }
+
+    /// Sends the request with the `Upgrade` header, e.g. `websocket` or `h2c`,
+    /// then takes the stream back after `101 Switching Protocols`.
+    pub async fn upgrade(
+        mut self,
+        request: Request<Vec<u8>>,
+    ) -> Result<Http1ClientUpgrade<S, SLEEP>, IoError> {
+        let (mut parts, body) = request.into_parts();
+        if !parts.headers.contains_key(UPGRADE) {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "missing Upgrade header",
+            ));
+        }
+        if !is_upgrade(&parts.headers) {
+            parts
+                .headers
+                .append(CONNECTION, HeaderValue::from_static(CONNECTION_UPGRADE));
+        }
+
+        self.write_request(Request::from_parts(parts, body)).await?;
+
+        let ((response, reason_phrase), body_framing) = self.read_final_head().await?;
+        if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+            let (stream, leftover) = self.into_parts();
+            return Ok(Http1ClientUpgrade::Switched {
+                response,
+                stream: Rewind::new(stream, leftover),
+            });
+        }
+
+        let body = self.read_body_to_end(&body_framing).await?;
+        let (parts, _) = response.into_parts();
+        Ok(Http1ClientUpgrade::Rejected {
+            response: Response::from_parts(parts, body),
+            reason_phrase,
+            stream: Box::new(self),
+        })
+    }
+
+    /// Sends `CONNECT host:port` to the proxy, then takes the tunnelled stream back after a 2xx response,
+    /// e.g. to be wrapped in TLS.
+    pub async fn connect_tunnel(
+        mut self,
+        authority: Authority,
+        proxy_authorization: Option<HeaderValue>,
+    ) -> Result<Rewind<S>, IoError> {
+        if authority.port().is_none() {
+            return Err(IoError::new(IoErrorKind::InvalidInput, "missing port"));
+        }
+
+        let mut request = Request::new(vec![]);
+        *request.method_mut() = Method::CONNECT;
+        *request.uri_mut() = authority.clone().into();
+        request.headers_mut().insert(
+            HOST,
+            HeaderValue::from_str(authority.as_str()).map_err(IoError::other)?,
+        );
+        if let Some(proxy_authorization) = proxy_authorization {
+            request
+                .headers_mut()
+                .insert(PROXY_AUTHORIZATION, proxy_authorization);
+        }
+
+        self.write_request(request).await?;
+
+        let (response, _) = self.read_response().await?;
+        if !response.status().is_success() {
+            return Err(IoError::other(format!(
+                "CONNECT failed, status: {}",
+                response.status()
+            )));
+        }
+
+        let (stream, leftover) = self.into_parts();
+        Ok(Rewind::new(stream, leftover))
+    }
+
+    pub async fn read_response_with_body_reader(
+        &mut self,
+    ) -> Result<(Response<Http1ClientBodyReader<'_, S, SLEEP>>, ReasonPhrase), IoError> {
+        let ((response, reason_phrase), _) = self.read_final_head().await?;
+
+        let (parts, _) = response.into_parts();
+        let response = Response::from_parts(parts, self.body_reader());
+
+        Ok((response, reason_phrase))
+    }
 }
 
 impl<S, SLEEP> AsyncRead for Http1ClientStream<S, SLEEP>
@@ -267,6 +710,23 @@ where
     }
 }
 
+pub enum Http1ClientUpgrade<S, SLEEP>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    SLEEP: Sleepble,
+{
+    /// The stream speaks the new protocol, it reads the bytes already read after the 101 response first.
+    Switched {
+        response: Response<()>,
+        stream: Rewind<S>,
+    },
+    Rejected {
+        response: Response<Vec<u8>>,
+        reason_phrase: ReasonPhrase,
+        stream: Box<Http1ClientStream<S, SLEEP>>,
+    },
+}
+
 //
 //
 //
@@ -278,12 +738,24 @@ pub type Http1ServerStreamInner<S, SLEEP> = Http1Stream<
     Http1ResponseEncoder,
     (Response<()>, ReasonPhrase),
 >;
+pub type Http1ServerBodyReader<'a, S, SLEEP> =
+    Http1BodyReader<'a, S, SLEEP, Http1RequestDecoder, Request<()>>;
 pub struct Http1ServerStream<S, SLEEP>
 where
     S: AsyncRead + AsyncWrite + Unpin + Send,
     SLEEP: Sleepble,
 {
     inner: Http1ServerStreamInner<S, SLEEP>,
+    keep_alive: bool,
+    closed: bool,
+    // The requests read but not responded yet, in request order.
+    pipeline: VecDeque<PendingRequest>,
+    pipeline_depth: usize,
+}
+struct PendingRequest {
+    method: Method,
+    version: Version,
+    is_persistent: bool,
 }
 impl<S, SLEEP> Deref for Http1ServerStream<S, SLEEP>
 where
@@ -320,6 +792,10 @@ where
     pub fn with(stream: S, decoder: Http1RequestDecoder, encoder: Http1ResponseEncoder) -> Self {
         Self {
             inner: Http1ServerStreamInner::new(stream, decoder, encoder),
+            keep_alive: true,
+            closed: false,
+            pipeline: VecDeque::new(),
+            pipeline_depth: usize::MAX,
         }
     }
 
@@ -329,13 +805,185 @@ where
     pub fn get_mut(&mut self) -> &mut S {
         &mut self.inner.stream
     }
+    pub fn get_read_trailers(&self) -> &HeaderMap<HeaderValue> {
+        self.decoder.get_trailers()
+    }
+    pub fn set_write_trailers(&mut self, trailers: HeaderMap<HeaderValue>) {
+        self.encoder.set_trailers(trailers)
+    }
+
     pub fn into_inner(self) -> Result<S, IoError> {
         if self.decoder.has_unparsed_bytes() {
-            return Err(IoError::new(IoErrorKind::Other, "has unparsed bytes"));
+            return Err(IoError::other("has unparsed bytes"));
         }
         Ok(self.inner.stream)
     }
+    /// Returns the stream with the bytes already read from it but not parsed,
+    /// e.g. the TLS ServerHello right after the response of CONNECT.
+    pub fn into_parts(mut self) -> (S, Vec<u8>) {
+        let leftover = self.decoder.take_unparsed_bytes();
+        (self.inner.stream, leftover)
+    }
+
+    /// Whether another request may be read after the current exchange.
+    pub fn can_reuse(&self) -> bool {
+        self.keep_alive
+    }
+
+    /// How many requests could be read before their responses are written, default is unlimited.
+    ///
+    /// Responses are always written in request order.
+    pub fn set_pipeline_depth(&mut self, value: usize) {
+        self.pipeline_depth = value.max(1);
+    }
+    pub fn get_pipeline_depth(&self) -> usize {
+        self.pipeline_depth
+    }
+    /// The number of requests read and waiting for the responses.
+    pub fn get_pipelined_count(&self) -> usize {
+        self.pipeline.len()
+    }
+    /// Whether bytes of the next request were already read, e.g. pipelined in the same segment.
+    pub fn has_buffered_request(&self) -> bool {
+        self.decoder.has_unparsed_bytes()
+    }
+    /// Waits for the first bytes of the next request, returns false when the client closed.
+    ///
+    /// Cancel safe, e.g. to close an idle connection at shutdown, while a request already
+    /// arriving is not dropped.
+    pub async fn wait_request(&mut self) -> Result<bool, IoError> {
+        if !self.decoder.is_idle() {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "read the body of the previous request first",
+            ));
+        }
+        if self.decoder.has_unparsed_bytes() {
+            return Ok(true);
+        }
+
+        let inner = &mut self.inner;
+        let n = inner
+            .decoder
+            .fill_buf::<_, SLEEP>(&mut inner.stream)
+            .await?;
+        Ok(n > 0)
+    }
+    fn is_answering_head(&self) -> bool {
+        self.pipeline
+            .front()
+            .map(|x| x.method == Method::HEAD)
+            .unwrap_or(false)
+    }
 
+    pub async fn read_head(&mut self) -> Result<(Request<()>, BodyFraming), IoError> {
+        if self.closed || !self.keep_alive {
+            return Err(IoError::new(
+                IoErrorKind::NotConnected,
+                "connection is marked close",
+            ));
+        }
+        if self.pipeline.len() >= self.pipeline_depth {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "pipeline depth reached, write the response first",
+            ));
+        }
+        if !self.decoder.is_idle() {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "read the body of the previous request first",
+            ));
+        }
+
+        let (request, body_framing) = match self.inner.read_head().await {
+            Ok(x) => x,
+            Err(err) => {
+                // The malformed request could still be answered, e.g. with 400, then close.
+                // Its version is unknown, so the response is not chunked.
+                if err.kind() == IoErrorKind::InvalidInput {
+                    self.pipeline.push_back(PendingRequest {
+                        method: Method::GET,
+                        version: Version::HTTP_10,
+                        is_persistent: false,
+                    });
+                    self.keep_alive = false;
+                }
+                return Err(err);
+            }
+        };
+
+        let is_persistent = is_persistent(request.headers(), &request.version());
+        self.pipeline.push_back(PendingRequest {
+            method: request.method().to_owned(),
+            version: request.version(),
+            is_persistent,
+        });
+        self.keep_alive = self.keep_alive && is_persistent;
+
+        Ok((request, body_framing))
+    }
+
+    /// Adds `Connection: close` to the final response when the connection can not be reused.
+    pub async fn write_head(
+        &mut self,
+        head: (Response<()>, ReasonPhrase),
+        body_framing: BodyFraming,
+    ) -> Result<(), IoError> {
+        if self.closed {
+            return Err(IoError::new(
+                IoErrorKind::NotConnected,
+                "connection is marked close",
+            ));
+        }
+
+        if self.pipeline.is_empty() {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "no request to respond, read the request first",
+            ));
+        }
+
+        let (mut response, reason_phrase) = head;
+
+        let is_final = !response.status().is_informational();
+        let mut is_closing = false;
+        if is_final {
+            // The final response answers the oldest request.
+            let request = self
+                .pipeline
+                .pop_front()
+                .ok_or_else(|| IoError::other("pipeline should not be empty"))?;
+            self.encoder.set_request_method(Some(request.method));
+
+            let is_response_persistent = is_persistent(response.headers(), &response.version());
+            is_closing = !request.is_persistent
+                || !is_response_persistent
+                || body_framing == BodyFraming::CloseDelimited;
+
+            if is_closing {
+                self.keep_alive = false;
+
+                if is_response_persistent {
+                    response
+                        .headers_mut()
+                        .insert(CONNECTION, HeaderValue::from_static(CLOSE));
+                }
+            }
+        }
+
+        self.inner
+            .write_head((response, reason_phrase), body_framing)
+            .await?;
+
+        if is_closing {
+            self.closed = true;
+        }
+
+        Ok(())
+    }
+
+    /// The body of a response to HEAD is not sent, only its Content-Length.
     pub async fn write_response(
         &mut self,
         response: Response<Vec<u8>>,
@@ -346,12 +994,14 @@ where
 
         let body_framing = BodyFraming::ContentLength(body.len());
 
+        let is_head = self.is_answering_head();
         self.write_head((head, reason_phrase), body_framing.clone())
             .await?;
 
         match body_framing {
+            _ if is_head => {}
             BodyFraming::Neither => {}
-            BodyFraming::ContentLength(n) if n == 0 => {}
+            BodyFraming::ContentLength(0) => {}
             _ => {
                 self.write_body(EncoderBody::Completed(body)).await?;
             }
@@ -360,31 +1010,209 @@ where
         Ok(())
     }
 
+    /// Without a content_length, the body is sent chunked,
+    /// or delimited by close if the request is not HTTP/1.1, the client may not understand chunked.
+    pub async fn write_response_with_body_reader<R: AsyncRead + Unpin>(
+        &mut self,
+        response: Response<R>,
+        reason_phrase: ReasonPhrase,
+        content_length: Option<usize>,
+    ) -> Result<(), IoError> {
+        let (parts, mut body) = response.into_parts();
+        let head = Response::from_parts(parts, ());
+
+        // The response answers the oldest request.
+        let request_version = self
+            .pipeline
+            .front()
+            .map(|x| x.version)
+            .unwrap_or(head.version());
+
+        let body_framing = match content_length {
+            Some(n) => BodyFraming::ContentLength(n),
+            None if request_version != Version::HTTP_11 => BodyFraming::CloseDelimited,
+            None => BodyFraming::Chunked,
+        };
+
+        let is_head = self.is_answering_head();
+        self.write_head((head, reason_phrase), body_framing.clone())
+            .await?;
+        match body_framing {
+            _ if is_head => {}
+            BodyFraming::ContentLength(0) => {}
+            _ => {
+                self.write_body_from_reader(&mut body, content_length)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// For a request with `Expect: 100-continue`, call it before read_body.
+    ///
+    /// Sends `100 Continue` if accept, otherwise `417 Expectation Failed` as the final response,
+    /// then the body must not be read and the connection is closed after.
+    /// Returns whether the body should be read.
+    ///
+    /// While the responses of earlier pipelined requests are not written, `100 Continue` is not sent,
+    /// the client sends the body after its own timeout, and `417 Expectation Failed` is an error.
+    pub async fn reply_expect_continue(
+        &mut self,
+        request: &Request<()>,
+        accept: bool,
+    ) -> Result<bool, IoError> {
+        // HTTP/1.0 clients do not wait for `100 Continue`.
+        if request.version() != Version::HTTP_11 || !is_expect_continue(request.headers()) {
+            return Ok(true);
+        }
+
+        // It would be read as an interim response of an earlier request.
+        let is_oldest = self.pipeline.len() <= 1;
+
+        if accept {
+            if is_oldest {
+                self.write_interim_response(StatusCode::CONTINUE, HeaderMap::new())
+                    .await?;
+            }
+            Ok(true)
+        } else {
+            if !is_oldest {
+                return Err(IoError::new(
+                    IoErrorKind::InvalidInput,
+                    "write the responses of the earlier requests first",
+                ));
+            }
+
+            // The unread body is in the way of the next request.
+            if let Some(request) = self.pipeline.front_mut() {
+                request.is_persistent = false;
+            }
+
+            let response = Response::builder()
+                .status(StatusCode::EXPECTATION_FAILED)
+                .body(())
+                .map_err(IoError::other)?;
+            // With content-length:0, the client does not read the body until close.
+            self.write_head((response, None), BodyFraming::ContentLength(0))
+                .await?;
+            Ok(false)
+        }
+    }
+
+    /// Sends `101 Switching Protocols` for an upgrade request, see `http1_spec::connection::is_upgrade`,
+    /// then takes the stream back, it reads the bytes already read after the request first.
+    ///
+    /// The response should have the `Upgrade` header of the chosen protocol.
+    pub async fn upgrade(mut self, response: Response<()>) -> Result<Rewind<S>, IoError> {
+        if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "should is 101 Switching Protocols",
+            ));
+        }
+        if !response.headers().contains_key(UPGRADE) {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "missing Upgrade header",
+            ));
+        }
+        if !self.decoder.is_idle() {
+            return Err(IoError::other("request body is not read"));
+        }
+
+        let mut response = response;
+        if !is_upgrade(response.headers()) {
+            response
+                .headers_mut()
+                .append(CONNECTION, HeaderValue::from_static(CONNECTION_UPGRADE));
+        }
+
+        self.write_head((response, None), BodyFraming::Neither)
+            .await?;
+
+        let (stream, leftover) = self.into_parts();
+        Ok(Rewind::new(stream, leftover))
+    }
+
+    /// Could be sent any times before the final response.
+    pub async fn write_interim_response(
+        &mut self,
+        status_code: StatusCode,
+        headers: HeaderMap<HeaderValue>,
+    ) -> Result<(), IoError> {
+        if !status_code.is_informational() || status_code == StatusCode::SWITCHING_PROTOCOLS {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "not an interim status",
+            ));
+        }
+
+        let mut response = Response::new(());
+        *response.status_mut() = status_code;
+        *response.headers_mut() = headers;
+
+        self.write_head((response, None), BodyFraming::Neither)
+            .await
+    }
+
+    /// Sends `103 Early Hints`, e.g. with `Link` headers of the resources to preload.
+    pub async fn write_early_hints(
+        &mut self,
+        headers: HeaderMap<HeaderValue>,
+    ) -> Result<(), IoError> {
+        let status_code = StatusCode::from_u16(EARLY_HINTS).map_err(IoError::other)?;
+
+        let mut response = Response::new(());
+        *response.status_mut() = status_code;
+        *response.headers_mut() = headers;
+
+        self.write_head(
+            (response, Some(b"Early Hints".to_vec())),
+            BodyFraming::Neither,
+        )
+        .await
+    }
+
+    /// Sends `100 Continue` first if the request expects it.
     pub async fn read_request(&mut self) -> Result<Request<Vec<u8>>, IoError> {
         let (request, body_framing) = self.read_head().await?;
 
-        let mut body = Vec::new();
         match body_framing {
             BodyFraming::Neither => {}
-            BodyFraming::ContentLength(n) if n == 0 => {}
-            _ => loop {
-                match self.read_body().await? {
-                    DecoderBody::Completed(bytes) => {
-                        body.extend_from_slice(&bytes);
-                        break;
-                    }
-                    DecoderBody::Partial(bytes) => {
-                        body.extend_from_slice(&bytes);
-                    }
-                }
-            },
+            BodyFraming::ContentLength(0) => {}
+            _ => {
+                self.reply_expect_continue(&request, true).await?;
+            }
         }
 
+        let body = self.read_body_to_end(&body_framing).await?;
+
         let (parts, _) = request.into_parts();
         let request = Request::from_parts(parts, body);
 
         Ok(request)
     }
+
+    /// Sends `100 Continue` first if the request expects it.
+    pub async fn read_request_with_body_reader(
+        &mut self,
+    ) -> Result<Request<Http1ServerBodyReader<'_, S, SLEEP>>, IoError> {
+        let (request, body_framing) = self.read_head().await?;
+
+        match body_framing {
+            BodyFraming::Neither => {}
+            BodyFraming::ContentLength(0) => {}
+            _ => {
+                self.reply_expect_continue(&request, true).await?;
+            }
+        }
+
+        let (parts, _) = request.into_parts();
+        let request = Request::from_parts(parts, self.body_reader());
+
+        Ok(request)
+    }
 }
 
 impl<S, SLEEP> AsyncRead for Http1ServerStream<S, SLEEP>
@@ -0,0 +1,254 @@
+use core::time::Duration;
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+};
+
+use async_io::Async;
+use async_sleep::impl_async_io::Timer;
+use async_trait::async_trait;
+use futures_lite::future::block_on;
+use http::{Method, Request, Response, StatusCode};
+
+use async_http1_lite::{
+    client::{Http1Client, Http1ClientConfig},
+    pool::{Connector, Http1ClientPool, Origin},
+    stream::Http1ServerStream,
+};
+
+struct TcpConnector;
+#[async_trait]
+impl Connector for TcpConnector {
+    type Stream = Async<TcpStream>;
+
+    async fn connect(&self, origin: &Origin) -> Result<Self::Stream, IoError> {
+        let ip = origin
+            .host()
+            .parse()
+            .map_err(|err| IoError::new(IoErrorKind::InvalidInput, err))?;
+        Async::<TcpStream>::connect(SocketAddr::new(ip, origin.port())).await
+    }
+}
+
+// Routes:
+// /echo replies the method and the body.
+// /301, /302, /303, /307 and /308 redirect to /echo, /loop redirects to itself.
+// /flaky closes the connection at the second request on it.
+// /slow replies after 1 second.
+fn serve() -> Result<SocketAddr, IoError> {
+    let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0))?;
+    let addr = listener.get_ref().local_addr()?;
+
+    thread::spawn(move || {
+        block_on(async {
+            while let Ok((stream, _)) = listener.accept().await {
+                thread::spawn(move || block_on(serve_connection(stream)));
+            }
+        })
+    });
+
+    Ok(addr)
+}
+
+async fn serve_connection(stream: Async<TcpStream>) {
+    let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(stream);
+
+    let mut n = 0;
+    while let Ok(request) = stream.read_request().await {
+        n += 1;
+
+        let response = match request.uri().path() {
+            "/echo" => Response::new(
+                format!(
+                    "{} {}",
+                    request.method(),
+                    String::from_utf8_lossy(request.body())
+                )
+                .into_bytes(),
+            ),
+            "/301" | "/302" | "/303" | "/307" | "/308" => Response::builder()
+                .status(request.uri().path()[1..].parse::<u16>().unwrap())
+                .header("Location", "/echo")
+                .body(vec![])
+                .unwrap(),
+            "/loop" => Response::builder()
+                .status(StatusCode::FOUND)
+                .header("Location", "loop")
+                .body(vec![])
+                .unwrap(),
+            "/flaky" if n > 1 => break,
+            "/slow" => {
+                async_io::Timer::after(Duration::from_secs(1)).await;
+                Response::new(vec![])
+            }
+            _ => Response::new(b"ok".to_vec()),
+        };
+
+        if stream.write_response(response, None).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[test]
+fn send() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let addr = serve()?;
+        let client: Http1Client<_, Timer> = Http1Client::new(TcpConnector);
+
+        let response = client
+            .send(
+                Request::post(format!("http://{addr}/echo"))
+                    .body(b"foo".to_vec())
+                    .unwrap(),
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), b"POST foo");
+
+        let response = client
+            .send(
+                Request::get(format!("http://{addr}/"))
+                    .body(vec![])
+                    .unwrap(),
+            )
+            .await?;
+        assert_eq!(response.body(), b"ok");
+        assert_eq!(
+            client
+                .get_pool()
+                .open_count(&Origin::from_uri(&format!("http://{addr}").parse()?)?),
+            1
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn send_with_redirects() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let addr = serve()?;
+        let client: Http1Client<_, Timer> = Http1Client::new(TcpConnector);
+
+        for (status, method, body) in [
+            (301, Method::POST, "GET "),
+            (302, Method::POST, "GET "),
+            (303, Method::PUT, "GET "),
+            (307, Method::POST, "POST foo"),
+            (308, Method::PUT, "PUT foo"),
+            (301, Method::PUT, "PUT foo"),
+        ] {
+            let response = client
+                .send(
+                    Request::builder()
+                        .method(method)
+                        .uri(format!("http://{addr}/{status}"))
+                        .body(b"foo".to_vec())
+                        .unwrap(),
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.body(), body.as_bytes(), "{status}");
+        }
+
+        let err = client
+            .send(
+                Request::get(format!("http://{addr}/loop"))
+                    .body(vec![])
+                    .unwrap(),
+            )
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "too many redirects");
+
+        //
+        let client: Http1Client<_, Timer> = Http1Client::with(
+            Http1ClientPool::new(TcpConnector),
+            Http1ClientConfig {
+                max_redirects: 0,
+                ..Default::default()
+            },
+        );
+        let response = client
+            .send(
+                Request::get(format!("http://{addr}/301"))
+                    .body(vec![])
+                    .unwrap(),
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn send_with_retries() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let addr = serve()?;
+        let client: Http1Client<_, Timer> = Http1Client::new(TcpConnector);
+
+        for _ in 0..2 {
+            let response = client
+                .send(
+                    Request::get(format!("http://{addr}/flaky"))
+                        .body(vec![])
+                        .unwrap(),
+                )
+                .await?;
+            assert_eq!(response.body(), b"ok");
+        }
+
+        // Not idempotent.
+        let client: Http1Client<_, Timer> = Http1Client::new(TcpConnector);
+        client
+            .send(
+                Request::post(format!("http://{addr}/flaky"))
+                    .body(vec![])
+                    .unwrap(),
+            )
+            .await?;
+        let err = client
+            .send(
+                Request::post(format!("http://{addr}/flaky"))
+                    .body(vec![])
+                    .unwrap(),
+            )
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), IoErrorKind::UnexpectedEof);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn send_with_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let addr = serve()?;
+        let client: Http1Client<_, Timer> = Http1Client::with(
+            Http1ClientPool::new(TcpConnector),
+            Http1ClientConfig {
+                timeout: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+        );
+
+        let err = client
+            .send(
+                Request::get(format!("http://{addr}/slow"))
+                    .body(vec![])
+                    .unwrap(),
+            )
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), IoErrorKind::TimedOut);
+
+        Ok(())
+    })
+}
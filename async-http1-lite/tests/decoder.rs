@@ -2,11 +2,15 @@ use std::io::ErrorKind as IoErrorKind;
 
 use async_sleep::impl_async_io::Timer;
 use futures_lite::future::block_on;
-use futures_util::io::Cursor;
-use http::{Method, Version};
-use http1_spec::body_framing::BodyFraming;
+use futures_util::{io::Cursor, stream, TryStreamExt as _};
+use http::{Method, StatusCode, Version};
+use http1_spec::body_framing::{BodyFraming, BodyFramingDetectError};
 
-use async_http1_lite::{decoder::Http1RequestDecoder, stream::Http1StreamDecoder};
+use async_http1_lite::{
+    body::DecoderBody,
+    decoder::{DecodeError, Http1RequestDecoder, Http1ResponseDecoder},
+    stream::Http1StreamDecoder,
+};
 
 #[test]
 fn request_simple() -> Result<(), Box<dyn std::error::Error>> {
@@ -51,3 +55,339 @@ fn request_simple() -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     })
 }
+
+#[test]
+fn response_chunked() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = Cursor::new(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\nHTTP/1.1 204 No Content\r\n\r\n",
+        );
+
+        let mut decoder = Http1ResponseDecoder::new(1024, None);
+
+        let ((response, _), body_framing) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_framing, BodyFraming::Chunked);
+
+        match Http1StreamDecoder::<_, Timer, _>::read_body(&mut decoder, &mut stream).await? {
+            DecoderBody::Completed(bytes) => assert_eq!(bytes, b"Wikipedia"),
+            DecoderBody::Partial(_) => panic!(),
+        }
+
+        let ((response, _), body_framing) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(body_framing, BodyFraming::Neither);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn response_chunked_partial() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = stream::iter(vec![
+            Ok(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWi".to_vec()),
+            Ok(b"ki\r".to_vec()),
+            Ok(b"\n5\r\npedia\r\n".to_vec()),
+            Ok(b"0\r\n".to_vec()),
+            Ok(b"\r\n".to_vec()),
+        ])
+        .into_async_read();
+
+        let mut decoder = Http1ResponseDecoder::new(1024, None);
+
+        let (_, body_framing) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(body_framing, BodyFraming::Chunked);
+
+        let mut bodies = vec![];
+        loop {
+            match Http1StreamDecoder::<_, Timer, _>::read_body(&mut decoder, &mut stream).await? {
+                DecoderBody::Completed(bytes) => {
+                    bodies.push(bytes);
+                    break;
+                }
+                DecoderBody::Partial(bytes) => bodies.push(bytes),
+            }
+        }
+        assert_eq!(
+            bodies,
+            vec![b"Wi".to_vec(), b"ki".to_vec(), b"pedia".to_vec(), vec![]]
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn response_chunked_invalid() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream =
+            Cursor::new("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nx\r\nWiki\r\n");
+
+        let mut decoder = Http1ResponseDecoder::new(1024, None);
+
+        Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+
+        let err = Http1StreamDecoder::<_, Timer, _>::read_body(&mut decoder, &mut stream)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), IoErrorKind::InvalidInput);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn response_chunked_with_trailers() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = Cursor::new(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n0\r\nChecksum: abc\r\n\r\nHTTP/1.1 204 No Content\r\n\r\n",
+        );
+
+        let mut decoder = Http1ResponseDecoder::new(1024, None);
+
+        Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert!(decoder.get_trailers().is_empty());
+
+        match Http1StreamDecoder::<_, Timer, _>::read_body(&mut decoder, &mut stream).await? {
+            DecoderBody::Completed(bytes) => assert_eq!(bytes, b"Wiki"),
+            DecoderBody::Partial(_) => panic!(),
+        }
+        assert_eq!(decoder.get_trailers().get("Checksum").unwrap(), "abc");
+
+        Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert!(decoder.get_trailers().is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn request_with_content_length_and_transfer_encoding() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = Cursor::new(
+            "POST / HTTP/1.1\r\nHost: foo.com\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n",
+        );
+
+        let mut decoder = Http1RequestDecoder::new(1024, None);
+
+        let err = Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), IoErrorKind::InvalidInput);
+        assert_eq!(
+            err.get_ref()
+                .and_then(|err| err.downcast_ref::<BodyFramingDetectError>()),
+            Some(&BodyFramingDetectError::ContentLengthWithTransferEncoding)
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn response_without_body() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = Cursor::new(
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHTTP/1.1 304 Not Modified\r\nContent-Length: 5\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo",
+        );
+
+        let mut decoder = Http1ResponseDecoder::new(1024, None);
+
+        decoder.set_request_method(Some(Method::HEAD));
+        let ((response, _), body_framing) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_framing, BodyFraming::Neither);
+
+        let ((response, _), body_framing) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(body_framing, BodyFraming::Neither);
+
+        let ((response, _), body_framing) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_framing, BodyFraming::ContentLength(3));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn response_close_delimited() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = stream::iter(vec![
+            Ok(b"HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\nWi".to_vec()),
+            Ok(b"ki".to_vec()),
+            Ok(b"pedia".to_vec()),
+        ])
+        .into_async_read();
+
+        let mut decoder = Http1ResponseDecoder::new(64, None);
+
+        let (_, body_framing) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(body_framing, BodyFraming::CloseDelimited);
+
+        let mut bodies = vec![];
+        loop {
+            match Http1StreamDecoder::<_, Timer, _>::read_body(&mut decoder, &mut stream).await? {
+                DecoderBody::Completed(bytes) => {
+                    bodies.push(bytes);
+                    break;
+                }
+                DecoderBody::Partial(bytes) => bodies.push(bytes),
+            }
+        }
+        assert_eq!(
+            bodies,
+            vec![b"Wi".to_vec(), b"ki".to_vec(), b"pedia".to_vec(), vec![]]
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn request_with_growing_buf() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let user_agent = "x".repeat(100);
+        let body = "y".repeat(100);
+        let mut stream = Cursor::new(format!(
+            "POST / HTTP/1.1\r\nUser-Agent: {user_agent}\r\nContent-Length: 100\r\n\r\n{body}GET /next HTTP/1.1\r\n\r\n"
+        ));
+
+        let mut decoder = Http1RequestDecoder::new(16, None);
+        decoder.set_buf_max_capacity(128);
+
+        let (request, body_framing) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(request.headers().get("User-Agent").unwrap(), &user_agent);
+        assert_eq!(body_framing, BodyFraming::ContentLength(100));
+
+        let mut bytes_all = vec![];
+        loop {
+            match Http1StreamDecoder::<_, Timer, _>::read_body(&mut decoder, &mut stream).await? {
+                DecoderBody::Completed(bytes) => {
+                    bytes_all.extend_from_slice(&bytes);
+                    break;
+                }
+                DecoderBody::Partial(bytes) => bytes_all.extend_from_slice(&bytes),
+            }
+        }
+        assert_eq!(bytes_all, body.as_bytes());
+
+        let (request, _) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(request.uri(), "/next");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn request_with_too_large_head() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = Cursor::new(format!(
+            "GET / HTTP/1.1\r\nUser-Agent: {}\r\n\r\n",
+            "x".repeat(100)
+        ));
+
+        let mut decoder = Http1RequestDecoder::new(16, None);
+        decoder.set_buf_max_capacity(64);
+
+        let err = Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.get_ref().unwrap().downcast_ref::<DecodeError>(),
+            Some(&DecodeError::HeadTooLarge)
+        );
+
+        Ok(())
+    })
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn request_body_bytes() -> Result<(), Box<dyn std::error::Error>> {
+    use async_http1_lite::body::DecoderBytesBody;
+
+    block_on(async {
+        let mut stream = stream::iter(vec![
+            Ok(b"POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\nWiki".to_vec()),
+            Ok(b"pedia".to_vec()),
+            Ok(b"GET / HTTP/1.1\r\n\r\n".to_vec()),
+        ])
+        .into_async_read();
+
+        let mut decoder = Http1RequestDecoder::new(64, None);
+
+        let (_, body_framing) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(body_framing, BodyFraming::ContentLength(9));
+
+        match Http1StreamDecoder::<_, Timer, _>::read_body_bytes(&mut decoder, &mut stream).await? {
+            DecoderBytesBody::Partial(bytes) => assert_eq!(bytes, "Wiki"),
+            DecoderBytesBody::Completed(_) => panic!(),
+        }
+        match Http1StreamDecoder::<_, Timer, _>::read_body_bytes(&mut decoder, &mut stream).await? {
+            DecoderBytesBody::Completed(bytes) => assert_eq!(bytes, "pedia"),
+            DecoderBytesBody::Partial(_) => panic!(),
+        }
+
+        let (request, body_framing) =
+            Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+        assert_eq!(request.method(), Method::GET);
+        assert_eq!(body_framing, BodyFraming::Neither);
+
+        Ok(())
+    })
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn request_body_bytes_share_buf() -> Result<(), Box<dyn std::error::Error>> {
+    use async_http1_lite::body::DecoderBytesBody;
+
+    block_on(async {
+        let mut stream = stream::iter(vec![
+            Ok(b"POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\nWiki".to_vec()),
+            Ok(b"pedia".to_vec()),
+        ])
+        .into_async_read();
+
+        let mut decoder = Http1RequestDecoder::new(1024, None);
+
+        Http1StreamDecoder::<_, Timer, _>::read_head(&mut decoder, &mut stream).await?;
+
+        let first =
+            match Http1StreamDecoder::<_, Timer, _>::read_body_bytes(&mut decoder, &mut stream)
+                .await?
+            {
+                DecoderBytesBody::Partial(bytes) => bytes,
+                DecoderBytesBody::Completed(_) => panic!(),
+            };
+        let second =
+            match Http1StreamDecoder::<_, Timer, _>::read_body_bytes(&mut decoder, &mut stream)
+                .await?
+            {
+                DecoderBytesBody::Completed(bytes) => bytes,
+                DecoderBytesBody::Partial(_) => panic!(),
+            };
+        assert_eq!(first, "Wiki");
+        assert_eq!(second, "pedia");
+
+        // Read into the same allocation, right after the handed out bytes.
+        assert_eq!(second.as_ptr(), first.as_ptr().wrapping_add(first.len()));
+
+        Ok(())
+    })
+}
@@ -1,11 +1,13 @@
 use async_sleep::impl_async_io::Timer;
 use futures_lite::future::block_on;
 use futures_util::io::Cursor;
-use http::Request;
+use http::{HeaderMap, Request, Response};
 use http1_spec::body_framing::BodyFraming;
 
 use async_http1_lite::{
-    body::EncoderBody, encoder::Http1RequestEncoder, stream::Http1StreamEncoder,
+    body::EncoderBody,
+    encoder::{Http1RequestEncoder, Http1ResponseEncoder},
+    stream::Http1StreamEncoder,
 };
 
 #[test]
@@ -71,3 +73,200 @@ fn request_with_body() -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     })
 }
+
+#[test]
+fn request_with_chunked_body() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = Cursor::new(vec![]);
+        let mut encoder = Http1RequestEncoder::new(1024);
+
+        for _ in 0..2 {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/")
+                .header("Host", "example.com")
+                .body(())
+                .unwrap();
+
+            Http1StreamEncoder::<_, Timer, _>::write_head(
+                &mut encoder,
+                &mut stream,
+                request,
+                BodyFraming::Chunked,
+            )
+            .await?;
+            Http1StreamEncoder::<_, Timer, _>::write_body(
+                &mut encoder,
+                &mut stream,
+                EncoderBody::Partial(b"Wikipedia in\r\n\r\n".to_vec()),
+            )
+            .await?;
+            Http1StreamEncoder::<_, Timer, _>::write_body(
+                &mut encoder,
+                &mut stream,
+                EncoderBody::Partial(vec![]),
+            )
+            .await?;
+            Http1StreamEncoder::<_, Timer, _>::write_body(
+                &mut encoder,
+                &mut stream,
+                EncoderBody::Completed(b"chunks.".to_vec()),
+            )
+            .await?;
+        }
+
+        assert_eq!(
+            stream.into_inner(),
+            b"POST / HTTP/1.1\r\nhost:example.com\r\ntransfer-encoding:chunked\r\n\r\n10\r\nWikipedia in\r\n\r\n\r\n7\r\nchunks.\r\n0\r\n\r\n"
+                .repeat(2)
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn response_with_chunked_body() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = Cursor::new(vec![]);
+        let response = Response::builder().status(200).body(()).unwrap();
+
+        let mut encoder = Http1ResponseEncoder::new(1024);
+        Http1StreamEncoder::<_, Timer, _>::write_head(
+            &mut encoder,
+            &mut stream,
+            (response, None),
+            BodyFraming::Chunked,
+        )
+        .await?;
+        Http1StreamEncoder::<_, Timer, _>::write_body(
+            &mut encoder,
+            &mut stream,
+            EncoderBody::Completed(vec![]),
+        )
+        .await?;
+
+        assert_eq!(
+            stream.into_inner(),
+            b"HTTP/1.1 200 OK\r\ntransfer-encoding:chunked\r\n\r\n0\r\n\r\n".to_vec()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn response_with_chunked_body_and_trailers() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = Cursor::new(vec![]);
+        let response = Response::builder().status(200).body(()).unwrap();
+
+        let mut encoder = Http1ResponseEncoder::new(1024);
+        let mut trailers = HeaderMap::new();
+        trailers.insert("Grpc-Status", "0".parse().unwrap());
+        encoder.set_trailers(trailers);
+
+        Http1StreamEncoder::<_, Timer, _>::write_head(
+            &mut encoder,
+            &mut stream,
+            (response, None),
+            BodyFraming::Chunked,
+        )
+        .await?;
+        Http1StreamEncoder::<_, Timer, _>::write_body(
+            &mut encoder,
+            &mut stream,
+            EncoderBody::Completed(b"foo".to_vec()),
+        )
+        .await?;
+
+        assert_eq!(
+            stream.into_inner(),
+            b"HTTP/1.1 200 OK\r\ntransfer-encoding:chunked\r\n\r\n3\r\nfoo\r\n0\r\ngrpc-status:0\r\n\r\n"
+                .to_vec()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn response_with_close_delimited_body() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream = Cursor::new(vec![]);
+        let response = Response::builder()
+            .status(200)
+            .header("Content-Length", "9")
+            .body(())
+            .unwrap();
+
+        let mut encoder = Http1ResponseEncoder::new(1024);
+        Http1StreamEncoder::<_, Timer, _>::write_head(
+            &mut encoder,
+            &mut stream,
+            (response, None),
+            BodyFraming::CloseDelimited,
+        )
+        .await?;
+        Http1StreamEncoder::<_, Timer, _>::write_body(
+            &mut encoder,
+            &mut stream,
+            EncoderBody::Partial(b"Wiki".to_vec()),
+        )
+        .await?;
+        Http1StreamEncoder::<_, Timer, _>::write_body(
+            &mut encoder,
+            &mut stream,
+            EncoderBody::Completed(b"pedia".to_vec()),
+        )
+        .await?;
+
+        assert_eq!(
+            stream.into_inner(),
+            b"HTTP/1.1 200 OK\r\n\r\nWikipedia".to_vec()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn request_connect() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut encoder = Http1RequestEncoder::new(1024);
+
+        for (uri, target) in [
+            ("example.com:8443", "example.com:8443"),
+            ("https://example.com", "example.com:443"),
+            ("http://user@example.com/", "example.com:80"),
+        ] {
+            let mut stream = Cursor::new(vec![]);
+            let request = Request::connect(uri).body(()).unwrap();
+            Http1StreamEncoder::<_, Timer, _>::write_head(
+                &mut encoder,
+                &mut stream,
+                request,
+                BodyFraming::Neither,
+            )
+            .await?;
+
+            assert_eq!(
+                stream.into_inner(),
+                format!("CONNECT {target} HTTP/1.1\r\n\r\n").as_bytes()
+            );
+        }
+
+        let mut stream = Cursor::new(vec![]);
+        let request = Request::connect("/").body(()).unwrap();
+        assert!(Http1StreamEncoder::<_, Timer, _>::write_head(
+            &mut encoder,
+            &mut stream,
+            request,
+            BodyFraming::Neither,
+        )
+        .await
+        .is_err());
+
+        Ok(())
+    })
+}
@@ -0,0 +1,249 @@
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use std::{
+    collections::VecDeque,
+    io::Error as IoError,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use async_sleep::impl_async_io::Timer;
+use async_trait::async_trait;
+use futures_lite::future::{block_on, poll_once};
+use futures_util::{AsyncRead, AsyncWrite};
+use http::{uri::Scheme, Request, Uri};
+
+use async_http1_lite::pool::{Connector, Http1ClientPool, Http1ClientPoolConfig, Origin};
+
+// Replies one response per request, then pending like an open socket, unless closed.
+struct MockStream {
+    responses: VecDeque<&'static [u8]>,
+    closed: bool,
+    requested: bool,
+}
+impl AsyncRead for MockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        if this.requested {
+            if let Some(bytes) = this.responses.pop_front() {
+                this.requested = false;
+                buf[..bytes.len()].copy_from_slice(bytes);
+                return Poll::Ready(Ok(bytes.len()));
+            }
+        }
+        if this.closed && this.responses.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        Poll::Pending
+    }
+}
+impl AsyncWrite for MockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        self.get_mut().requested = true;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), IoError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), IoError>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+// The responses and whether closed after them, per connection.
+type MockScript = Vec<(Vec<&'static [u8]>, bool)>;
+
+struct MockConnector {
+    streams: Mutex<MockScript>,
+    count: AtomicUsize,
+}
+impl MockConnector {
+    fn new(streams: MockScript) -> Self {
+        Self {
+            streams: Mutex::new(streams),
+            count: AtomicUsize::new(0),
+        }
+    }
+}
+#[async_trait]
+impl Connector for MockConnector {
+    type Stream = MockStream;
+
+    async fn connect(&self, _origin: &Origin) -> Result<Self::Stream, IoError> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        let mut streams = self.streams.lock().unwrap();
+        if streams.is_empty() {
+            return Err(IoError::other("no more streams"));
+        }
+        let (responses, closed) = streams.remove(0);
+        Ok(MockStream {
+            responses: responses.into(),
+            closed,
+            requested: false,
+        })
+    }
+}
+
+const OK: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo";
+const OK_BAR: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nbar";
+
+async fn get(pool: &Http1ClientPool<MockConnector, Timer>) -> Result<Vec<u8>, IoError> {
+    let origin = Origin::from_uri(&Uri::from_static("http://example.com/"))?;
+    let mut conn = pool.acquire(&origin).await?;
+    conn.write_request(Request::get("/").body(vec![]).unwrap())
+        .await?;
+    let (response, _) = conn.read_response().await?;
+    Ok(response.into_body())
+}
+
+#[test]
+fn origin() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(
+        Origin::from_uri(&Uri::from_static("https://Example.com/foo"))?,
+        Origin::new(Scheme::HTTPS, "example.com", 443)
+    );
+    assert_eq!(
+        Origin::from_uri(&Uri::from_static("http://example.com:8080"))?.to_string(),
+        "http://example.com:8080"
+    );
+    assert!(Origin::from_uri(&Uri::from_static("/foo")).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn reuse() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let pool: Http1ClientPool<_, Timer> =
+            Http1ClientPool::new(MockConnector::new(vec![(vec![OK, OK_BAR], false)]));
+
+        assert_eq!(get(&pool).await?, b"foo");
+        assert_eq!(pool.idle_count(), 1);
+        assert_eq!(get(&pool).await?, b"bar");
+        assert_eq!(pool.get_connector().count.load(Ordering::SeqCst), 1);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn not_reuse_half_closed() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let pool: Http1ClientPool<_, Timer> = Http1ClientPool::new(MockConnector::new(vec![
+            (vec![OK], true),
+            (vec![OK], false),
+        ]));
+
+        assert_eq!(get(&pool).await?, b"foo");
+        assert_eq!(pool.idle_count(), 1);
+        assert_eq!(get(&pool).await?, b"foo");
+        assert_eq!(pool.get_connector().count.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.idle_count(), 1);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn not_reuse_without_keep_alive() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let pool: Http1ClientPool<_, Timer> = Http1ClientPool::new(MockConnector::new(vec![(
+            vec![b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 3\r\n\r\nfoo"],
+            false,
+        )]));
+
+        assert_eq!(get(&pool).await?, b"foo");
+        assert_eq!(pool.idle_count(), 0);
+
+        // Exchange not completed.
+        let pool: Http1ClientPool<_, Timer> =
+            Http1ClientPool::new(MockConnector::new(vec![(vec![OK], false)]));
+        let origin = Origin::new(Scheme::HTTP, "example.com", 80);
+        let mut conn = pool.acquire(&origin).await?;
+        conn.write_request(Request::get("/").body(vec![]).unwrap())
+            .await?;
+        drop(conn);
+        assert_eq!(pool.idle_count(), 0);
+        assert_eq!(pool.open_count(&origin), 0);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn max_per_host() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let pool: Http1ClientPool<_, Timer> = Http1ClientPool::with(
+            MockConnector::new(vec![(vec![OK], false), (vec![OK], false)]),
+            Http1ClientPoolConfig {
+                max_per_host: 1,
+                ..Default::default()
+            },
+        );
+        let origin = Origin::new(Scheme::HTTP, "example.com", 80);
+
+        let conn = pool.acquire(&origin).await?;
+        assert_eq!(pool.open_count(&origin), 1);
+
+        let mut acquiring = Box::pin(pool.acquire(&origin));
+        assert!(poll_once(&mut acquiring).await.is_none());
+
+        drop(conn);
+        let conn = acquiring.await?;
+        assert_eq!(pool.open_count(&origin), 1);
+        drop(conn);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn max_idle_and_idle_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let pool: Http1ClientPool<_, Timer> = Http1ClientPool::with(
+            MockConnector::new(vec![(vec![OK], false)]),
+            Http1ClientPoolConfig {
+                max_idle: 0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(get(&pool).await?, b"foo");
+        assert_eq!(pool.idle_count(), 0);
+
+        //
+        let pool: Http1ClientPool<_, Timer> = Http1ClientPool::with(
+            MockConnector::new(vec![(vec![OK], false)]),
+            Http1ClientPoolConfig {
+                idle_timeout: Duration::from_millis(10),
+                ..Default::default()
+            },
+        );
+        assert_eq!(get(&pool).await?, b"foo");
+        assert_eq!(pool.idle_count(), 1);
+
+        std::thread::sleep(Duration::from_millis(20));
+        pool.evict_expired();
+        assert_eq!(pool.idle_count(), 0);
+        assert_eq!(
+            pool.open_count(&Origin::new(Scheme::HTTP, "example.com", 80)),
+            0
+        );
+
+        Ok(())
+    })
+}
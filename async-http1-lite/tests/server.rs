@@ -0,0 +1,325 @@
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use std::{
+    io::{Error as IoError, Read as _, Write as _},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use async_io::Async;
+use async_sleep::impl_async_io::Timer;
+use async_trait::async_trait;
+use futures_lite::future::block_on;
+use futures_util::{future::pending, io::Cursor, AsyncRead, AsyncWrite};
+use http::{Request, Response};
+
+use async_http1_lite::server::{Acceptor, Http1Server, Http1ServerConfig, Http1ServerShutdown};
+
+struct DuplexStream {
+    reader: Cursor<Vec<u8>>,
+    writer: Vec<u8>,
+}
+impl DuplexStream {
+    fn new(bytes: &[u8]) -> Self {
+        Self {
+            reader: Cursor::new(bytes.to_vec()),
+            writer: vec![],
+        }
+    }
+}
+impl AsyncRead for DuplexStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.get_mut().reader).poll_read(cx, buf)
+    }
+}
+impl AsyncWrite for DuplexStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.get_mut().writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().writer).poll_close(cx)
+    }
+}
+
+// Replies the path, /slow after 100 milliseconds, /error fails.
+async fn handle(request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, IoError> {
+    match request.uri().path() {
+        "/slow" => {
+            async_io::Timer::after(Duration::from_millis(100)).await;
+        }
+        "/error" => return Err(IoError::other("foo")),
+        _ => {}
+    }
+    Ok(Response::new(request.uri().path().as_bytes().to_vec()))
+}
+
+#[test]
+fn serve_connection() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let server: Http1Server<_, Timer> = Http1Server::new(handle);
+
+        let mut stream = DuplexStream::new(
+            b"GET /a HTTP/1.1\r\n\r\nHEAD /b HTTP/1.1\r\n\r\nGET /error HTTP/1.1\r\n\r\nGET /c HTTP/1.1\r\nConnection: close\r\n\r\nGET /d HTTP/1.1\r\n\r\n",
+        );
+        server.serve_connection(&mut stream).await?;
+
+        assert_eq!(
+            stream.writer,
+            b"HTTP/1.1 200 OK\r\ncontent-length:2\r\n\r\n/aHTTP/1.1 200 OK\r\ncontent-length:2\r\n\r\nHTTP/1.1 500 Internal Server Error\r\ncontent-length:0\r\n\r\nHTTP/1.1 200 OK\r\nconnection:close\r\ncontent-length:2\r\n\r\n/c"
+        );
+        assert_eq!(server.get_shutdown().connection_count(), 0);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn serve_connection_with_pipelining() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let server: Http1Server<_, Timer> = Http1Server::with(
+            handle,
+            Http1ServerConfig {
+                pipeline_depth: 3,
+                ..Default::default()
+            },
+        );
+
+        let mut stream = DuplexStream::new(
+            b"GET /slow HTTP/1.1\r\n\r\nGET /a HTTP/1.1\r\n\r\nGET /slow HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\n\r\n",
+        );
+        server.serve_connection(&mut stream).await?;
+
+        assert_eq!(
+            stream.writer,
+            b"HTTP/1.1 200 OK\r\ncontent-length:5\r\n\r\n/slowHTTP/1.1 200 OK\r\ncontent-length:2\r\n\r\n/aHTTP/1.1 200 OK\r\ncontent-length:5\r\n\r\n/slowHTTP/1.1 200 OK\r\ncontent-length:2\r\n\r\n/b"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn serve_connection_with_pipelined_expect_continue() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let server: Http1Server<_, Timer> = Http1Server::with(
+            handle,
+            Http1ServerConfig {
+                pipeline_depth: 2,
+                ..Default::default()
+            },
+        );
+
+        let mut stream = DuplexStream::new(
+            b"GET /a HTTP/1.1\r\n\r\nPOST /b HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\nfoo",
+        );
+        server.serve_connection(&mut stream).await?;
+
+        // 100 Continue would be read as an interim response of /a.
+        assert_eq!(
+            stream.writer,
+            b"HTTP/1.1 200 OK\r\ncontent-length:2\r\n\r\n/aHTTP/1.1 200 OK\r\ncontent-length:2\r\n\r\n/b"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn serve_connection_with_bad_request() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let server: Http1Server<_, Timer> = Http1Server::new(handle);
+
+        let mut stream =
+            DuplexStream::new(b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\nfoo\r\n\r\n");
+        assert!(server.serve_connection(&mut stream).await.is_err());
+
+        assert_eq!(
+            stream.writer,
+            b"HTTP/1.1 200 OK\r\ncontent-length:2\r\n\r\n/aHTTP/1.1 400 Bad Request\r\nconnection:close\r\ncontent-length:0\r\n\r\n"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn serve_connection_with_too_long_header() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let server: Http1Server<_, Timer> = Http1Server::new(handle);
+
+        let mut stream = DuplexStream::new(
+            format!("GET /a HTTP/1.1\r\nfoo: {}\r\n\r\n", "x".repeat(1024)).as_bytes(),
+        );
+        assert!(server.serve_connection(&mut stream).await.is_err());
+
+        assert_eq!(
+            stream.writer,
+            b"HTTP/1.1 431 Request Header Fields Too Large\r\nconnection:close\r\ncontent-length:0\r\n\r\n"
+        );
+
+        Ok(())
+    })
+}
+
+struct TcpAcceptor(Async<TcpListener>);
+#[async_trait]
+impl Acceptor for TcpAcceptor {
+    type Stream = Async<TcpStream>;
+
+    async fn accept(&self) -> Result<Self::Stream, IoError> {
+        self.0.accept().await.map(|(stream, _)| stream)
+    }
+}
+
+#[test]
+fn serve_with_graceful_shutdown() -> Result<(), Box<dyn std::error::Error>> {
+    let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0))?;
+    let addr = listener.get_ref().local_addr()?;
+
+    let server: Http1Server<_, Timer> = Http1Server::new(handle);
+    let shutdown = server.get_shutdown().to_owned();
+
+    let serving = thread::spawn(move || {
+        block_on(server.serve(TcpAcceptor(listener), |future| {
+            thread::spawn(move || block_on(future));
+        }))
+    });
+
+    let mut idle = TcpStream::connect(addr)?;
+    idle.write_all(b"GET /a HTTP/1.1\r\n\r\n")?;
+    let mut buf = [0; 39];
+    idle.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"HTTP/1.1 200 OK\r\ncontent-length:2\r\n\r\n/a");
+
+    let mut busy = TcpStream::connect(addr)?;
+    busy.write_all(b"GET /slow HTTP/1.1\r\n\r\n")?;
+    thread::sleep(Duration::from_millis(50));
+
+    shutdown.shutdown();
+
+    // In-flight request is answered, then closed.
+    let mut bytes = vec![];
+    busy.read_to_end(&mut bytes)?;
+    assert_eq!(
+        bytes,
+        b"HTTP/1.1 200 OK\r\nconnection:close\r\ncontent-length:5\r\n\r\n/slow"
+    );
+
+    // Idle one is closed.
+    assert_eq!(idle.read(&mut buf)?, 0);
+
+    serving.join().unwrap()?;
+    assert_eq!(shutdown.connection_count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn serve_with_graceful_shutdown_during_request() -> Result<(), Box<dyn std::error::Error>> {
+    let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0))?;
+    let addr = listener.get_ref().local_addr()?;
+
+    let server: Http1Server<_, Timer> = Http1Server::new(handle);
+    let shutdown = server.get_shutdown().to_owned();
+
+    let serving = thread::spawn(move || {
+        block_on(server.serve(TcpAcceptor(listener), |future| {
+            thread::spawn(move || block_on(future));
+        }))
+    });
+
+    let mut partial = TcpStream::connect(addr)?;
+    partial.write_all(b"POST /a HTTP/1.1\r\nContent-Length: 3\r\n\r\nf")?;
+    thread::sleep(Duration::from_millis(50));
+
+    shutdown.shutdown();
+    thread::sleep(Duration::from_millis(50));
+
+    // The request already arriving is read to the end and answered, then closed.
+    partial.write_all(b"oo")?;
+    let mut bytes = vec![];
+    partial.read_to_end(&mut bytes)?;
+    assert_eq!(
+        bytes,
+        b"HTTP/1.1 200 OK\r\nconnection:close\r\ncontent-length:2\r\n\r\n/a"
+    );
+
+    serving.join().unwrap()?;
+    assert_eq!(shutdown.connection_count(), 0);
+
+    Ok(())
+}
+
+// Accepts the scripted streams, then shuts down the server.
+struct MockAcceptor {
+    script: Mutex<Vec<Result<DuplexStream, IoError>>>,
+    shutdown: Http1ServerShutdown,
+}
+#[async_trait]
+impl Acceptor for MockAcceptor {
+    type Stream = DuplexStream;
+
+    async fn accept(&self) -> Result<Self::Stream, IoError> {
+        let next = self.script.lock().unwrap().pop();
+        match next {
+            Some(ret) => ret,
+            None => {
+                self.shutdown.shutdown();
+                pending().await
+            }
+        }
+    }
+}
+
+#[test]
+fn serve_with_accept_errors() -> Result<(), Box<dyn std::error::Error>> {
+    let count = Arc::new(AtomicUsize::new(0));
+    let server: Http1Server<_, Timer> = Http1Server::new({
+        let count = count.clone();
+        move |request| {
+            count.fetch_add(1, Ordering::SeqCst);
+            handle(request)
+        }
+    });
+    let shutdown = server.get_shutdown().to_owned();
+
+    let acceptor = MockAcceptor {
+        script: Mutex::new(vec![
+            Ok(DuplexStream::new(
+                b"GET /slow HTTP/1.1\r\nConnection: close\r\n\r\n",
+            )),
+            Err(IoError::other("too many open files")),
+        ]),
+        shutdown: shutdown.to_owned(),
+    };
+
+    block_on(server.serve(acceptor, |future| {
+        thread::spawn(move || block_on(future));
+    }))?;
+
+    // Accepted after the error, and answered before serve returns.
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+    assert_eq!(shutdown.connection_count(), 0);
+
+    Ok(())
+}
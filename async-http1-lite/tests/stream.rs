@@ -1,13 +1,74 @@
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use std::{
+    io::Error as IoError,
+    sync::{Arc, Mutex},
+};
+
 use async_sleep::impl_async_io::Timer;
 use futures_lite::future::block_on;
-use futures_util::{io::Cursor, AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use futures_util::{
+    io::Cursor, AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _, TryStreamExt as _,
+};
+use http::{HeaderMap, Request, Response, StatusCode, Version};
+use http1_spec::{body_framing::BodyFraming, connection::is_upgrade};
 
-use async_http1_lite::stream::{Http1ClientStream, Http1ServerStream};
+use async_http1_lite::{
+    body::EncoderBody,
+    stream::{Http1ClientStream, Http1ClientUpgrade, Http1ServerStream},
+};
 
 struct MyStream<S>(S)
 where
     S: AsyncRead + AsyncWrite;
 
+struct DuplexStream<R = Cursor<Vec<u8>>> {
+    reader: R,
+    writer: Vec<u8>,
+}
+impl DuplexStream {
+    fn new(bytes: &[u8]) -> Self {
+        Self::with_reader(Cursor::new(bytes.to_vec()))
+    }
+}
+impl<R> DuplexStream<R> {
+    fn with_reader(reader: R) -> Self {
+        Self {
+            reader,
+            writer: vec![],
+        }
+    }
+}
+impl<R: AsyncRead + Unpin> AsyncRead for DuplexStream<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.get_mut().reader).poll_read(cx, buf)
+    }
+}
+impl<R: Unpin> AsyncWrite for DuplexStream<R> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.get_mut().writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().writer).poll_close(cx)
+    }
+}
+
 #[test]
 fn client_get_ref() -> Result<(), Box<dyn std::error::Error>> {
     let cursor = Cursor::new(vec![]);
@@ -41,6 +102,35 @@ fn client_into_inner() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn client_into_parts() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 200 Connection established\r\n\r\n\x16\x03\x03",
+        ));
+
+        stream
+            .write_request(
+                Request::connect("example.com:443")
+                    .header("Host", "example.com:443")
+                    .body(vec![])
+                    .unwrap(),
+            )
+            .await?;
+        let (response, _) = stream.read_response().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (stream, leftover) = stream.into_parts();
+        assert_eq!(leftover, b"\x16\x03\x03");
+        assert_eq!(
+            stream.writer,
+            b"CONNECT example.com:443 HTTP/1.1\r\nhost:example.com:443\r\n\r\n"
+        );
+
+        Ok(())
+    })
+}
+
 #[test]
 fn client_read_and_write() -> Result<(), Box<dyn std::error::Error>> {
     block_on(async {
@@ -59,6 +149,562 @@ fn client_read_and_write() -> Result<(), Box<dyn std::error::Error>> {
     })
 }
 
+#[test]
+fn client_read_response_with_chunked() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let cursor = Cursor::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5\r\npedia\r\nE\r\n in\r\n\r\nchunks.\r\n0\r\n\r\n"
+                .to_vec(),
+        );
+
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(cursor);
+
+        let (response, _) = stream.read_response().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), b"Wikipedia in\r\n\r\nchunks.");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_read_response_until_close() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let cursor = Cursor::new(b"HTTP/1.1 200 OK\r\n\r\nWikipedia in\r\n\r\nchunks.".to_vec());
+
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(cursor);
+
+        let (response, _) = stream.read_response().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), b"Wikipedia in\r\n\r\nchunks.");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_read_http10_response_with_transfer_encoding() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let cursor = Cursor::new(
+            b"HTTP/1.0 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 3\r\nConnection: keep-alive\r\n\r\nfooHTTP/1.1 200 OK\r\n\r\n"
+                .to_vec(),
+        );
+
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(cursor);
+
+        // Faulty framing, read until close.
+        let (response, _) = stream.read_response().await?;
+        assert_eq!(response.body(), b"fooHTTP/1.1 200 OK\r\n\r\n");
+        assert!(!stream.can_reuse());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_read_empty_response_from_server() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut server: Http1ServerStream<_, Timer> = Http1ServerStream::new(DuplexStream::new(
+            b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n",
+        ));
+        server.read_request().await?;
+        server.write_response(Response::new(vec![]), None).await?;
+        server.read_request().await?;
+        server
+            .write_response(Response::new(b"foo".to_vec()), None)
+            .await?;
+        let bytes = server.into_inner()?.writer;
+        assert_eq!(
+            bytes,
+            b"HTTP/1.1 200 OK\r\ncontent-length:0\r\n\r\nHTTP/1.1 200 OK\r\ncontent-length:3\r\n\r\nfoo"
+        );
+
+        let mut client: Http1ClientStream<_, Timer> = Http1ClientStream::new(Cursor::new(bytes));
+
+        let (response, _) = client.read_response().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), b"");
+
+        let (response, _) = client.read_response().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), b"foo");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_read_and_write_trailers() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let cursor = Cursor::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n0\r\nDigest: sha-256=x\r\n\r\n"
+                .to_vec(),
+        );
+
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(cursor);
+
+        let (response, _) = stream.read_response().await?;
+        assert_eq!(response.body(), b"Wiki");
+        assert_eq!(
+            stream.get_read_trailers().get("Digest").unwrap(),
+            "sha-256=x"
+        );
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("Digest", "sha-256=y".parse().unwrap());
+        stream.set_write_trailers(trailers);
+        stream
+            .write_head(Request::post("/").body(()).unwrap(), BodyFraming::Chunked)
+            .await?;
+        stream
+            .write_body(EncoderBody::Completed(b"foo".to_vec()))
+            .await?;
+
+        assert!(stream
+            .get_ref()
+            .get_ref()
+            .ends_with(b"3\r\nfoo\r\n0\r\ndigest:sha-256=y\r\n\r\n"));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_read_response_to_head() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+        ));
+
+        stream
+            .write_request(Request::head("/").body(vec![]).unwrap())
+            .await?;
+        let (response, _) = stream.read_response().await?;
+        assert_eq!(response.headers().get("Content-Length").unwrap(), "5");
+        assert_eq!(response.body(), b"");
+
+        stream
+            .write_request(Request::get("/").body(vec![]).unwrap())
+            .await?;
+        let (response, _) = stream.read_response().await?;
+        assert_eq!(response.body(), b"hello");
+
+        assert_eq!(
+            stream.get_ref().writer,
+            b"HEAD / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_read_response_with_body_reader() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let cursor = Cursor::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo"
+                .to_vec(),
+        );
+
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(cursor);
+
+        let (response, _) = stream.read_response_with_body_reader().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body_reader = response.into_body();
+        let mut buf = [0u8; 3];
+        let mut body = vec![];
+        loop {
+            let n = body_reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            body.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(body, b"Wikipedia");
+        assert!(body_reader.is_completed());
+        drop(body_reader);
+
+        let (response, _) = stream.read_response_with_body_reader().await?;
+        let mut body = vec![];
+        response.into_body().read_to_end(&mut body).await?;
+        assert_eq!(body, b"foo");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_write_request_with_body_reader() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ClientStream<_, Timer> =
+            Http1ClientStream::new(DuplexStream::new(b""));
+
+        stream
+            .write_request_with_body_reader(
+                Request::post("/").body(Cursor::new(b"Wikipedia")).unwrap(),
+                Some(9),
+            )
+            .await?;
+        stream
+            .write_request_with_body_reader(
+                Request::post("/").body(Cursor::new(b"Wikipedia")).unwrap(),
+                None,
+            )
+            .await?;
+
+        assert_eq!(
+            stream.get_ref().writer,
+            b"POST / HTTP/1.1\r\ncontent-length:9\r\n\r\nWikipediaPOST / HTTP/1.1\r\ntransfer-encoding:chunked\r\n\r\n9\r\nWikipedia\r\n0\r\n\r\n"
+        );
+
+        let err = stream
+            .write_request_with_body_reader(
+                Request::post("/").body(Cursor::new(b"Wiki")).unwrap(),
+                Some(9),
+            )
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        let mut stream: Http1ClientStream<_, Timer> =
+            Http1ClientStream::new(DuplexStream::new(b""));
+        let err = stream
+            .write_request_with_body_reader(
+                Request::post("/")
+                    .version(Version::HTTP_10)
+                    .body(Cursor::new(b"Wikipedia"))
+                    .unwrap(),
+                None,
+            )
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(stream.get_ref().writer.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_send_pipelined() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\n1HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\n3",
+        ));
+
+        let results = stream
+            .send_pipelined(vec![
+                Request::get("/1").body(vec![]).unwrap(),
+                Request::head("/2").body(vec![]).unwrap(),
+                Request::get("/3").body(vec![]).unwrap(),
+            ])
+            .await;
+        assert_eq!(results.len(), 3);
+
+        let bodies = results
+            .into_iter()
+            .map(|x| x.map(|(response, _)| response.into_body()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(bodies, vec![b"1".to_vec(), vec![], b"3".to_vec()]);
+
+        assert_eq!(
+            stream.get_ref().writer,
+            b"GET /1 HTTP/1.1\r\n\r\nHEAD /2 HTTP/1.1\r\n\r\nGET /3 HTTP/1.1\r\n\r\n"
+        );
+        assert!(stream.can_reuse());
+
+        //
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\n1",
+        ));
+
+        let results = stream
+            .send_pipelined(vec![
+                Request::get("/1").body(vec![]).unwrap(),
+                Request::get("/2").body(vec![]).unwrap(),
+                Request::get("/3").body(vec![]).unwrap(),
+            ])
+            .await;
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        for result in &results[1..] {
+            assert_eq!(
+                result.as_ref().err().unwrap().kind(),
+                std::io::ErrorKind::ConnectionAborted
+            );
+        }
+        assert!(!stream.can_reuse());
+
+        //
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 1\r\n\r\n1HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\n2",
+        ));
+
+        let results = stream
+            .send_pipelined(vec![
+                Request::get("/1").body(vec![]).unwrap(),
+                Request::get("/2").body(vec![]).unwrap(),
+            ])
+            .await;
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1].as_ref().err().unwrap().kind(),
+            std::io::ErrorKind::ConnectionAborted
+        );
+        assert!(!stream.can_reuse());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_keep_alive() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\nHTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ));
+
+        stream
+            .write_request(Request::get("/").body(vec![]).unwrap())
+            .await?;
+        stream.read_response().await?;
+        assert!(stream.can_reuse());
+
+        stream
+            .write_request(Request::get("/").body(vec![]).unwrap())
+            .await?;
+        stream.read_response().await?;
+        assert!(!stream.can_reuse());
+
+        let err = stream
+            .write_request(Request::get("/").body(vec![]).unwrap())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotConnected);
+
+        //
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n",
+        ));
+
+        stream
+            .write_request(Request::get("/").body(vec![]).unwrap())
+            .await?;
+        stream.read_response().await?;
+        assert!(!stream.can_reuse());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_write_request_with_expect_continue() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+        ));
+
+        let response = stream
+            .write_request_with_expect_continue(
+                Request::post("/").body(b"foo".to_vec()).unwrap(),
+                Duration::from_secs(1),
+            )
+            .await?;
+        assert!(response.is_none());
+        assert_eq!(
+            stream.get_ref().writer,
+            b"POST / HTTP/1.1\r\nexpect:100-continue\r\ncontent-length:3\r\n\r\nfoo"
+        );
+
+        let (response, _) = stream.read_response().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The 417 from the server stream, the connection stays open.
+        let mut server: Http1ServerStream<_, Timer> = Http1ServerStream::new(DuplexStream::new(
+            b"POST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\n",
+        ));
+        let (request, _) = server.read_head().await?;
+        assert!(!server.reply_expect_continue(&request, false).await?);
+        let bytes = server.get_ref().writer.to_owned();
+
+        let mut stream: Http1ClientStream<_, Timer> =
+            Http1ClientStream::new(DuplexStream::with_reader(
+                futures_util::StreamExt::chain(
+                    futures_util::stream::iter(vec![Ok(bytes)]),
+                    futures_util::stream::pending(),
+                )
+                .into_async_read(),
+            ));
+        stream.set_read_timeout(Duration::from_millis(500));
+
+        let (response, _) = stream
+            .write_request_with_expect_continue(
+                Request::post("/").body(b"foo".to_vec()).unwrap(),
+                Duration::from_secs(1),
+            )
+            .await?
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::EXPECTATION_FAILED);
+        assert!(!stream.get_ref().writer.ends_with(b"foo"));
+        assert!(!stream.can_reuse());
+
+        //
+        let mut stream: Http1ClientStream<_, Timer> =
+            Http1ClientStream::new(DuplexStream::with_reader(
+                futures_util::stream::pending::<Result<Vec<u8>, IoError>>().into_async_read(),
+            ));
+
+        let response = stream
+            .write_request_with_expect_continue(
+                Request::post("/").body(b"foo".to_vec()).unwrap(),
+                Duration::from_millis(50),
+            )
+            .await?;
+        assert!(response.is_none());
+        assert!(stream.get_ref().writer.ends_with(b"\r\n\r\nfoo"));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_read_response_with_interim_responses() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let cursor = Cursor::new(
+            b"HTTP/1.1 102 Processing\r\n\r\nHTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo"
+                .to_vec(),
+        );
+
+        let mut stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(cursor);
+
+        let interim_responses = Arc::new(Mutex::new(vec![]));
+        let interim_responses_cloned = interim_responses.clone();
+        stream.set_interim_response_callback(Some(Box::new(move |status_code, headers| {
+            interim_responses_cloned
+                .lock()
+                .unwrap()
+                .push((status_code, headers.to_owned()));
+        })));
+
+        let (response, _) = stream.read_response().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), b"foo");
+
+        let interim_responses = interim_responses.lock().unwrap();
+        assert_eq!(interim_responses.len(), 2);
+        assert_eq!(interim_responses[0].0, StatusCode::PROCESSING);
+        assert_eq!(interim_responses[1].0.as_u16(), 103);
+        assert_eq!(
+            interim_responses[1].1.get("Link").unwrap(),
+            "</style.css>; rel=preload"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_upgrade() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\nframe",
+        ));
+
+        let request = Request::get("/chat")
+            .header("Upgrade", "websocket")
+            .body(vec![])
+            .unwrap();
+        match stream.upgrade(request).await? {
+            Http1ClientUpgrade::Switched {
+                response,
+                mut stream,
+            } => {
+                assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+                assert_eq!(
+                    stream.get_ref().writer,
+                    b"GET /chat HTTP/1.1\r\nupgrade:websocket\r\nconnection:upgrade\r\n\r\n"
+                );
+                assert_eq!(stream.get_leftover(), b"frame");
+
+                let mut buf = vec![];
+                stream.read_to_end(&mut buf).await?;
+                assert_eq!(buf, b"frame");
+            }
+            Http1ClientUpgrade::Rejected { .. } => panic!(),
+        }
+
+        //
+        let stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo",
+        ));
+
+        let request = Request::get("/")
+            .header("Upgrade", "h2c")
+            .header("Connection", "Upgrade, HTTP2-Settings")
+            .header("HTTP2-Settings", "AAMAAABkAARAAAAAAAIAAAAA")
+            .body(vec![])
+            .unwrap();
+        match stream.upgrade(request).await? {
+            Http1ClientUpgrade::Switched { .. } => panic!(),
+            Http1ClientUpgrade::Rejected {
+                response, stream, ..
+            } => {
+                assert_eq!(response.body(), b"foo");
+                assert!(stream.can_reuse());
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_connect_tunnel() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 200 Connection established\r\n\r\n\x16\x03\x03",
+        ));
+
+        let mut stream = stream
+            .connect_tunnel(
+                "example.com:443".parse()?,
+                Some("Basic dXNlcjpwYXNz".parse()?),
+            )
+            .await?;
+        assert_eq!(
+            stream.get_ref().writer,
+            b"CONNECT example.com:443 HTTP/1.1\r\nhost:example.com:443\r\nproxy-authorization:Basic dXNlcjpwYXNz\r\n\r\n"
+        );
+
+        // The leftover is read first, then the stream.
+        let mut buf = [0; 2];
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"\x16\x03");
+        stream.write_all(b"\x16\x03\x01").await?;
+        assert!(stream.get_ref().writer.ends_with(b"\r\n\r\n\x16\x03\x01"));
+
+        let (_, leftover) = stream.into_parts();
+        assert_eq!(leftover, b"\x03");
+
+        //
+        let stream: Http1ClientStream<_, Timer> = Http1ClientStream::new(DuplexStream::new(
+            b"HTTP/1.1 407 Proxy Authentication Required\r\nContent-Length: 0\r\n\r\n",
+        ));
+
+        let err = stream
+            .connect_tunnel("example.com:443".parse()?, None)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("407"));
+
+        Ok(())
+    })
+}
+
 #[test]
 fn client_asyncread_asyncwrite_bound() -> Result<(), Box<dyn std::error::Error>> {
     let cursor = Cursor::new(b"".to_vec());
@@ -101,6 +747,22 @@ fn server_into_inner() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn server_into_parts() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let cursor = Cursor::new(b"GET / HTTP/1.1\r\n\r\nGET /next HTTP/1.1\r\n".to_vec());
+
+        let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(cursor);
+
+        stream.read_request().await?;
+
+        let (_, leftover) = stream.into_parts();
+        assert_eq!(leftover, b"GET /next HTTP/1.1\r\n");
+
+        Ok(())
+    })
+}
+
 #[test]
 fn server_read_and_write() -> Result<(), Box<dyn std::error::Error>> {
     block_on(async {
@@ -119,6 +781,330 @@ fn server_read_and_write() -> Result<(), Box<dyn std::error::Error>> {
     })
 }
 
+#[test]
+fn server_read_request_with_body_reader() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let cursor = Cursor::new(
+            b"POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\nWikipediaGET / HTTP/1.1\r\n\r\n".to_vec(),
+        );
+
+        let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(cursor);
+
+        let request = stream.read_request_with_body_reader().await?;
+        let mut body = vec![];
+        request.into_body().read_to_end(&mut body).await?;
+        assert_eq!(body, b"Wikipedia");
+
+        let request = stream.read_request_with_body_reader().await?;
+        assert_eq!(request.uri(), "/");
+        let mut body = vec![];
+        request.into_body().read_to_end(&mut body).await?;
+        assert!(body.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_write_response_with_body_reader() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(DuplexStream::new(
+            b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n",
+        ));
+
+        stream.read_head().await?;
+        stream
+            .write_response_with_body_reader(Response::new(Cursor::new(b"Wiki")), None, None)
+            .await?;
+
+        // The response is HTTP/1.1, but the HTTP/1.0 client does not understand chunked.
+        stream.read_head().await?;
+        stream
+            .write_response_with_body_reader(Response::new(Cursor::new(b"pedia")), None, None)
+            .await?;
+
+        assert_eq!(
+            stream.get_ref().writer,
+            b"HTTP/1.1 200 OK\r\ntransfer-encoding:chunked\r\n\r\n4\r\nWiki\r\n0\r\n\r\nHTTP/1.1 200 OK\r\nconnection:close\r\n\r\npedia"
+        );
+        assert!(!stream.can_reuse());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_keep_alive() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(DuplexStream::new(
+            b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.0\r\n\r\nGET / HTTP/1.1\r\n\r\n",
+        ));
+
+        stream.read_request().await?;
+        assert!(stream.can_reuse());
+        stream
+            .write_response(Response::new(b"a".to_vec()), None)
+            .await?;
+
+        stream.read_request().await?;
+        assert!(!stream.can_reuse());
+        stream
+            .write_response(Response::new(b"b".to_vec()), None)
+            .await?;
+
+        assert_eq!(
+            stream.get_ref().writer,
+            b"HTTP/1.1 200 OK\r\ncontent-length:1\r\n\r\naHTTP/1.1 200 OK\r\nconnection:close\r\ncontent-length:1\r\n\r\nb"
+        );
+
+        let err = stream.read_request().await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotConnected);
+        let err = stream
+            .write_response(Response::new(b"c".to_vec()), None)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotConnected);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_reply_expect_continue() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(DuplexStream::new(
+            b"POST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\nfooPOST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\nbar",
+        ));
+
+        let request = stream.read_request().await?;
+        assert_eq!(request.body(), b"foo");
+        stream.write_response(Response::new(vec![]), None).await?;
+
+        let (request, _) = stream.read_head().await?;
+        assert!(!stream.reply_expect_continue(&request, false).await?);
+        assert!(!stream.can_reuse());
+
+        assert_eq!(
+            stream.get_ref().writer,
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\ncontent-length:0\r\n\r\nHTTP/1.1 417 Expectation Failed\r\nconnection:close\r\ncontent-length:0\r\n\r\n"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_read_request_with_expect_continue() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(DuplexStream::new(
+            b"POST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\nfooPOST / HTTP/1.0\r\nConnection: keep-alive\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\nbar",
+        ));
+
+        let request = stream.read_request_with_body_reader().await?;
+        let mut body = vec![];
+        request.into_body().read_to_end(&mut body).await?;
+        assert_eq!(body, b"foo");
+        stream.write_response(Response::new(vec![]), None).await?;
+
+        // Ignored in HTTP/1.0.
+        let (request, _) = stream.read_head().await?;
+        assert!(stream.reply_expect_continue(&request, false).await?);
+        assert!(stream.can_reuse());
+
+        assert_eq!(
+            stream.get_ref().writer,
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\ncontent-length:0\r\n\r\n"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_write_early_hints() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ServerStream<_, Timer> =
+            Http1ServerStream::new(DuplexStream::new(b"GET / HTTP/1.1\r\n\r\n"));
+
+        stream.read_request().await?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Link", "</style.css>; rel=preload".parse().unwrap());
+        stream.write_early_hints(headers).await?;
+        stream
+            .write_response(Response::new(b"foo".to_vec()), None)
+            .await?;
+
+        assert_eq!(
+            stream.get_ref().writer,
+            b"HTTP/1.1 103 Early Hints\r\nlink:</style.css>; rel=preload\r\n\r\nHTTP/1.1 200 OK\r\ncontent-length:3\r\n\r\nfoo"
+        );
+        assert!(stream.can_reuse());
+
+        let err = stream
+            .write_interim_response(StatusCode::OK, HeaderMap::new())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_upgrade() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(DuplexStream::new(
+            b"GET /chat HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\nframe",
+        ));
+
+        let request = stream.read_request().await?;
+        assert!(is_upgrade(request.headers()));
+
+        let response = Response::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header("Upgrade", "websocket")
+            .body(())
+            .unwrap();
+        let stream = stream.upgrade(response).await?;
+        assert_eq!(
+            stream.get_ref().writer,
+            b"HTTP/1.1 101 Switching Protocols\r\nupgrade:websocket\r\nconnection:upgrade\r\n\r\n"
+        );
+        assert_eq!(stream.get_leftover(), b"frame");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_pipelining() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(DuplexStream::new(
+            b"GET /1 HTTP/1.1\r\n\r\nPOST /2 HTTP/1.1\r\nContent-Length: 3\r\n\r\nfooGET /3 HTTP/1.1\r\nConnection: close\r\n\r\n",
+        ));
+        stream.set_pipeline_depth(2);
+
+        let request_1 = stream.read_request().await?;
+        assert_eq!(request_1.uri(), "/1");
+        assert!(stream.has_buffered_request());
+
+        let request_2 = stream.read_request().await?;
+        assert_eq!(request_2.uri(), "/2");
+        assert_eq!(request_2.body(), b"foo");
+        assert!(stream.has_buffered_request());
+        assert_eq!(stream.get_pipelined_count(), 2);
+
+        let err = stream.read_request().await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        stream
+            .write_response(Response::new(b"1".to_vec()), None)
+            .await?;
+
+        let request_3 = stream.read_request().await?;
+        assert_eq!(request_3.uri(), "/3");
+        assert!(!stream.has_buffered_request());
+
+        stream
+            .write_response(Response::new(b"2".to_vec()), None)
+            .await?;
+        assert!(!stream
+            .get_ref()
+            .writer
+            .ends_with(b"connection:close\r\n\r\n2"));
+
+        stream
+            .write_response(Response::new(b"3".to_vec()), None)
+            .await?;
+        assert_eq!(stream.get_pipelined_count(), 0);
+        assert!(!stream.can_reuse());
+
+        assert_eq!(
+            stream.get_ref().writer,
+            b"HTTP/1.1 200 OK\r\ncontent-length:1\r\n\r\n1HTTP/1.1 200 OK\r\ncontent-length:1\r\n\r\n2HTTP/1.1 200 OK\r\nconnection:close\r\ncontent-length:1\r\n\r\n3"
+        );
+
+        let err = stream
+            .write_response(Response::new(b"4".to_vec()), None)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotConnected);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_pipelining_with_expect_continue() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(DuplexStream::new(
+            b"GET /1 HTTP/1.1\r\n\r\nPOST /2 HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\nfoo",
+        ));
+
+        let request_1 = stream.read_request().await?;
+        assert_eq!(request_1.uri(), "/1");
+
+        // No 100 Continue before the response of /1.
+        let (request_2, _) = stream.read_head().await?;
+        assert_eq!(request_2.uri(), "/2");
+        let err = stream
+            .reply_expect_continue(&request_2, false)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(stream.reply_expect_continue(&request_2, true).await?);
+        let mut body = vec![];
+        stream.body_reader().read_to_end(&mut body).await?;
+        assert_eq!(body, b"foo");
+
+        stream
+            .write_response(Response::new(b"1".to_vec()), None)
+            .await?;
+        stream
+            .write_response(Response::new(b"2".to_vec()), None)
+            .await?;
+
+        assert_eq!(
+            stream.get_ref().writer,
+            b"HTTP/1.1 200 OK\r\ncontent-length:1\r\n\r\n1HTTP/1.1 200 OK\r\ncontent-length:1\r\n\r\n2"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_pipelining_out_of_order() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut stream: Http1ServerStream<_, Timer> = Http1ServerStream::new(DuplexStream::new(
+            b"POST /1 HTTP/1.1\r\nContent-Length: 3\r\n\r\nfooGET /2 HTTP/1.1\r\n\r\n",
+        ));
+
+        // No request to respond.
+        let err = stream
+            .write_response(Response::new(vec![]), None)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        stream.read_head().await?;
+        // The body of the previous request is not read.
+        let err = stream.read_head().await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(stream.get_pipelined_count(), 1);
+
+        assert!(stream.get_ref().writer.is_empty());
+
+        Ok(())
+    })
+}
+
 #[test]
 fn server_asyncread_asyncwrite_bound() -> Result<(), Box<dyn std::error::Error>> {
     let cursor = Cursor::new(b"".to_vec());
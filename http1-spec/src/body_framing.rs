@@ -2,7 +2,7 @@ use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 
 use http::{
     header::{CONTENT_LENGTH, TRANSFER_ENCODING},
-    HeaderMap, HeaderValue, Version,
+    HeaderMap, HeaderValue, Method, StatusCode, Version,
 };
 
 use crate::CHUNKED;
@@ -16,6 +16,8 @@ pub enum BodyFraming {
     ContentLength(usize),
     Chunked,
     Neither,
+    /// Response only, the body ends when the server closes the connection.
+    CloseDelimited,
 }
 
 impl BodyFraming {
@@ -33,35 +35,171 @@ impl BodyFraming {
     }
 }
 
+//
+//
+//
+#[derive(Debug, PartialEq, Eq)]
+pub enum BodyFramingDetectError {
+    InvalidContentLength,
+    MismatchedContentLength,
+    InvalidTransferEncoding,
+    ChunkedNotFinal,
+    ContentLengthWithTransferEncoding,
+    TransferEncodingInHttp10,
+}
+impl core::fmt::Display for BodyFramingDetectError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+impl std::error::Error for BodyFramingDetectError {}
+impl From<BodyFramingDetectError> for IoError {
+    fn from(err: BodyFramingDetectError) -> IoError {
+        IoError::new(IoErrorKind::InvalidInput, err)
+    }
+}
+
+//
+//
+//
+// ref https://www.rfc-editor.org/rfc/rfc9112#section-6.3
 pub trait BodyFramingDetector {
-    fn detect(&self) -> Result<BodyFraming, IoError>;
+    fn detect(&self) -> Result<BodyFraming, BodyFramingDetectError>;
 }
+
+// Request
 impl BodyFramingDetector for (&HeaderMap<HeaderValue>, &Version) {
-    fn detect(&self) -> Result<BodyFraming, IoError> {
+    fn detect(&self) -> Result<BodyFraming, BodyFramingDetectError> {
         let (headers, version) = *self;
 
-        if let Some(header_value) = headers.get(CONTENT_LENGTH) {
-            let value_str = header_value
-                .to_str()
-                .map_err(|err| IoError::new(IoErrorKind::InvalidInput, err))?;
-            let value: usize = value_str
-                .parse()
-                .map_err(|err| IoError::new(IoErrorKind::InvalidInput, err))?;
-            return Ok(BodyFraming::ContentLength(value));
+        let content_length = parse_content_length(headers)?;
+
+        if headers.contains_key(TRANSFER_ENCODING) {
+            if content_length.is_some() {
+                return Err(BodyFramingDetectError::ContentLengthWithTransferEncoding);
+            }
+
+            // The framing is faulty, the body of an HTTP/1.0 request can not be delimited.
+            if version != &Version::HTTP_11 {
+                return Err(BodyFramingDetectError::TransferEncodingInHttp10);
+            }
+
+            if is_chunked_final(headers)? {
+                return Ok(BodyFraming::Chunked);
+            }
+            return Err(BodyFramingDetectError::ChunkedNotFinal);
+        }
+
+        Ok(content_length
+            .map(BodyFraming::ContentLength)
+            .unwrap_or(BodyFraming::Neither))
+    }
+}
+
+// Response
+impl BodyFramingDetector for (&HeaderMap<HeaderValue>, &Version, &StatusCode) {
+    fn detect(&self) -> Result<BodyFraming, BodyFramingDetectError> {
+        let (headers, version, status_code) = *self;
+
+        if status_code.is_informational()
+            || status_code == &StatusCode::NO_CONTENT
+            || status_code == &StatusCode::NOT_MODIFIED
+        {
+            return Ok(BodyFraming::Neither);
+        }
+
+        // The framing is faulty, do not trust Content-Length and close after the body.
+        if version != &Version::HTTP_11 && headers.contains_key(TRANSFER_ENCODING) {
+            return Ok(BodyFraming::CloseDelimited);
         }
 
-        if version == &Version::HTTP_11 {
-            if let Some(header_value) = headers.get(TRANSFER_ENCODING) {
-                if header_value == CHUNKED {
-                    return Ok(BodyFraming::Chunked);
+        // Transfer-Encoding overrides Content-Length.
+        if headers.contains_key(TRANSFER_ENCODING) {
+            return match is_chunked_final(headers) {
+                Ok(true) => Ok(BodyFraming::Chunked),
+                // The client can not tell where the body ends, read until close.
+                Ok(false) | Err(BodyFramingDetectError::ChunkedNotFinal) => {
+                    Ok(BodyFraming::CloseDelimited)
                 }
+                Err(err) => Err(err),
+            };
+        }
+
+        Ok(parse_content_length(headers)?
+            .map(BodyFraming::ContentLength)
+            .unwrap_or(BodyFraming::CloseDelimited))
+    }
+}
+
+// Response to the request with the Method
+impl BodyFramingDetector for (&HeaderMap<HeaderValue>, &Version, &StatusCode, &Method) {
+    fn detect(&self) -> Result<BodyFraming, BodyFramingDetectError> {
+        let (headers, version, status_code, method) = *self;
+
+        if method == Method::HEAD || (method == Method::CONNECT && status_code.is_success()) {
+            return Ok(BodyFraming::Neither);
+        }
+
+        (headers, version, status_code).detect()
+    }
+}
+
+fn parse_content_length(
+    headers: &HeaderMap<HeaderValue>,
+) -> Result<Option<usize>, BodyFramingDetectError> {
+    let mut content_length = None;
+    for header_value in headers.get_all(CONTENT_LENGTH) {
+        let value_str = header_value
+            .to_str()
+            .map_err(|_| BodyFramingDetectError::InvalidContentLength)?;
+        // e.g. "5, 5"
+        for value_str in value_str.split(',').map(|x| x.trim_matches(OWS)) {
+            // parse accepts a leading sign, Content-Length does not.
+            if value_str.is_empty() || !value_str.bytes().all(|x| x.is_ascii_digit()) {
+                return Err(BodyFramingDetectError::InvalidContentLength);
+            }
+            let value: usize = value_str
+                .parse()
+                .map_err(|_| BodyFramingDetectError::InvalidContentLength)?;
+            match content_length {
+                Some(n) if n != value => {
+                    return Err(BodyFramingDetectError::MismatchedContentLength)
+                }
+                _ => content_length = Some(value),
             }
         }
+    }
+    Ok(content_length)
+}
 
-        Ok(BodyFraming::Neither)
+fn is_chunked_final(headers: &HeaderMap<HeaderValue>) -> Result<bool, BodyFramingDetectError> {
+    let mut codings = vec![];
+    for header_value in headers.get_all(TRANSFER_ENCODING) {
+        let value_str = header_value
+            .to_str()
+            .map_err(|_| BodyFramingDetectError::InvalidTransferEncoding)?;
+        codings.extend(
+            value_str
+                .split(',')
+                .map(|x| x.trim_matches(OWS))
+                .filter(|x| !x.is_empty()),
+        );
+    }
+
+    match codings.split_last() {
+        Some((last, others)) => {
+            // chunked must not be applied more than once.
+            if others.iter().any(|x| x.eq_ignore_ascii_case(CHUNKED)) {
+                return Err(BodyFramingDetectError::ChunkedNotFinal);
+            }
+            Ok(last.eq_ignore_ascii_case(CHUNKED))
+        }
+        None => Err(BodyFramingDetectError::InvalidTransferEncoding),
     }
 }
 
+const OWS: &[char] = &[' ', '\t'];
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,8 +217,8 @@ mod tests {
         header_map.clear();
         header_map.insert("Transfer-Encoding", "chunked".parse().unwrap());
         assert_eq!(
-            (&header_map, &Version::HTTP_10).detect()?,
-            BodyFraming::Neither
+            (&header_map, &Version::HTTP_10).detect(),
+            Err(BodyFramingDetectError::TransferEncodingInHttp10)
         );
         assert_eq!(
             (&header_map, &Version::HTTP_11).detect()?,
@@ -95,4 +233,177 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn detect_request_smuggling() {
+        let mut header_map = HeaderMap::new();
+
+        // CL.TE / TE.CL
+        header_map.insert("Content-Length", "5".parse().unwrap());
+        header_map.insert("Transfer-Encoding", "chunked".parse().unwrap());
+        assert_eq!(
+            (&header_map, &Version::HTTP_11).detect(),
+            Err(BodyFramingDetectError::ContentLengthWithTransferEncoding)
+        );
+        assert_eq!(
+            (&header_map, &Version::HTTP_10).detect(),
+            Err(BodyFramingDetectError::ContentLengthWithTransferEncoding)
+        );
+
+        // chunked is final
+        header_map.clear();
+        header_map.insert("Transfer-Encoding", "gzip, Chunked".parse().unwrap());
+        assert_eq!(
+            (&header_map, &Version::HTTP_11).detect(),
+            Ok(BodyFraming::Chunked)
+        );
+        header_map.clear();
+        header_map.append("Transfer-Encoding", "gzip".parse().unwrap());
+        header_map.append("Transfer-Encoding", "chunked".parse().unwrap());
+        assert_eq!(
+            (&header_map, &Version::HTTP_11).detect(),
+            Ok(BodyFraming::Chunked)
+        );
+
+        // chunked is not final
+        for value in ["chunked, gzip", "gzip", "chunked, chunked", ","] {
+            header_map.clear();
+            header_map.insert("Transfer-Encoding", value.parse().unwrap());
+            assert!(
+                (&header_map, &Version::HTTP_11).detect().is_err(),
+                "{value}"
+            );
+            assert_eq!(
+                (&header_map, &Version::HTTP_10).detect(),
+                Err(BodyFramingDetectError::TransferEncodingInHttp10),
+                "{value}"
+            );
+        }
+
+        // Content-Length
+        header_map.clear();
+        header_map.append("Content-Length", "5".parse().unwrap());
+        header_map.append("Content-Length", "5, 5".parse().unwrap());
+        assert_eq!(
+            (&header_map, &Version::HTTP_11).detect(),
+            Ok(BodyFraming::ContentLength(5))
+        );
+        header_map.append("Content-Length", "6".parse().unwrap());
+        assert_eq!(
+            (&header_map, &Version::HTTP_11).detect(),
+            Err(BodyFramingDetectError::MismatchedContentLength)
+        );
+
+        for value in ["+5", "-5", "0x5", "", "5 5", "99999999999999999999999"] {
+            header_map.clear();
+            header_map.insert("Content-Length", value.parse().unwrap());
+            assert_eq!(
+                (&header_map, &Version::HTTP_11).detect(),
+                Err(BodyFramingDetectError::InvalidContentLength),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn detect_response() {
+        let mut header_map = HeaderMap::new();
+
+        // Transfer-Encoding overrides Content-Length.
+        header_map.insert("Content-Length", "5".parse().unwrap());
+        header_map.insert("Transfer-Encoding", "chunked".parse().unwrap());
+        assert_eq!(
+            (&header_map, &Version::HTTP_11, &StatusCode::OK).detect(),
+            Ok(BodyFraming::Chunked)
+        );
+        assert_eq!(
+            (&header_map, &Version::HTTP_10, &StatusCode::OK).detect(),
+            Ok(BodyFraming::CloseDelimited)
+        );
+
+        header_map.clear();
+        header_map.insert("Content-Length", "+5".parse().unwrap());
+        assert_eq!(
+            (&header_map, &Version::HTTP_11, &StatusCode::OK).detect(),
+            Err(BodyFramingDetectError::InvalidContentLength)
+        );
+
+        // Delimited by connection close.
+        header_map.clear();
+        assert_eq!(
+            (&header_map, &Version::HTTP_10, &StatusCode::OK).detect(),
+            Ok(BodyFraming::CloseDelimited)
+        );
+        assert_eq!(
+            (&header_map, &Version::HTTP_11, &StatusCode::OK).detect(),
+            Ok(BodyFraming::CloseDelimited)
+        );
+        for value in ["gzip", "chunked, gzip"] {
+            header_map.insert("Transfer-Encoding", value.parse().unwrap());
+            assert_eq!(
+                (&header_map, &Version::HTTP_11, &StatusCode::OK).detect(),
+                Ok(BodyFraming::CloseDelimited),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn detect_response_without_body() {
+        let mut header_map = HeaderMap::new();
+        header_map.insert("Content-Length", "5".parse().unwrap());
+
+        for status_code in [
+            StatusCode::CONTINUE,
+            StatusCode::SWITCHING_PROTOCOLS,
+            StatusCode::NO_CONTENT,
+            StatusCode::NOT_MODIFIED,
+        ] {
+            assert_eq!(
+                (&header_map, &Version::HTTP_11, &status_code).detect(),
+                Ok(BodyFraming::Neither)
+            );
+        }
+
+        assert_eq!(
+            (
+                &header_map,
+                &Version::HTTP_11,
+                &StatusCode::OK,
+                &Method::HEAD
+            )
+                .detect(),
+            Ok(BodyFraming::Neither)
+        );
+        assert_eq!(
+            (
+                &header_map,
+                &Version::HTTP_11,
+                &StatusCode::OK,
+                &Method::CONNECT
+            )
+                .detect(),
+            Ok(BodyFraming::Neither)
+        );
+        assert_eq!(
+            (
+                &header_map,
+                &Version::HTTP_11,
+                &StatusCode::FORBIDDEN,
+                &Method::CONNECT
+            )
+                .detect(),
+            Ok(BodyFraming::ContentLength(5))
+        );
+        assert_eq!(
+            (
+                &header_map,
+                &Version::HTTP_11,
+                &StatusCode::OK,
+                &Method::GET
+            )
+                .detect(),
+            Ok(BodyFraming::ContentLength(5))
+        );
+    }
 }
@@ -1,6 +1,8 @@
 use core::num::ParseIntError;
 use std::io::{BufRead, Error as IoError, ErrorKind as IoErrorKind};
 
+use crate::head_parser::HeadParseError;
+
 //
 //
 //
@@ -15,8 +17,12 @@ pub enum BodyParseError {
     ReadError(IoError),
     TooLongChunksOfLength,
     InvalidChunksOfLength(Option<ParseIntError>),
+    TooLargeChunksOfLength,
+    TooLongChunksOfExtensions,
+    InvalidChunksOfExtensions,
     TooLongChunksOfCRLF,
     InvalidCRLF,
+    InvalidTrailer(HeadParseError),
 }
 impl core::fmt::Display for BodyParseError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
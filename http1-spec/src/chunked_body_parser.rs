@@ -1,64 +1,108 @@
+use core::num::IntErrorKind;
 use std::io::{BufRead, Read as _};
 
+use http::{HeaderMap, HeaderValue};
+
 use crate::{
     body_parser::{BodyParseError, BodyParseOutput, BodyParser},
-    CR, CRLF, LF,
+    head_parser::{parse_header, HeadParseConfig, HeadParseError},
+    CR, CRLF, HTAB, LF, SP,
 };
 
 //
 //
 //
-const LENGTH_MAX_LEN: usize = 4; // b"FFFF"
+const LENGTH_MAX_LEN: usize = 20; // b"FFFFFFFFFFFFFFFF" with some leading zeros
 const DATA_DEFAULT_LEN: usize = 512;
+const EXTENSIONS_DEFAULT_MAX_LEN: usize = 1024;
+
+pub type ChunkExtension = (String, Option<String>);
+pub type ChunkExtensionsCallback = Box<dyn FnMut(usize, &[ChunkExtension]) + Send>;
 
 //
 //
 //
-#[derive(Default)]
 pub struct ChunkedBodyParser {
+    pub trailers: HeaderMap<HeaderValue>,
+    //
+    trailers_config: HeadParseConfig,
     //
     state: State,
     length_buf: Vec<u8>,
-    length: u16,
+    length: usize,
+    chunk_max_len: usize,
+    trailers_len: usize,
+    chunk_extensions_max_len: usize,
+    chunk_extensions_callback: Option<ChunkExtensionsCallback>,
     data_buf: Vec<u8>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
 enum State {
+    #[default]
     Idle,
     WaitLengthParse,
     WaitDataParse,
     WaitDataParsing,
-    WaitCRLFParse(ActionAfterCRLFParsed),
+    WaitCRLFParse,
+    WaitTrailerParse,
 }
-impl Default for State {
+
+impl Default for ChunkedBodyParser {
     fn default() -> Self {
-        Self::Idle
+        Self::new()
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum ActionAfterCRLFParsed {
-    Continue,
-    Break,
-}
-
 impl ChunkedBodyParser {
     pub fn new() -> Self {
-        Self {
-            length_buf: Vec::with_capacity(LENGTH_MAX_LEN),
-            data_buf: vec![0u8; DATA_DEFAULT_LEN],
-            ..Default::default()
-        }
+        Self::with_data_buf(vec![0u8; DATA_DEFAULT_LEN])
     }
 
     pub fn with_data_buf(data_buf: Vec<u8>) -> Self {
         Self {
+            trailers: HeaderMap::new(),
+            trailers_config: Default::default(),
+            state: Default::default(),
             length_buf: Vec::with_capacity(LENGTH_MAX_LEN),
+            length: 0,
+            chunk_max_len: usize::MAX,
+            trailers_len: 0,
+            chunk_extensions_max_len: EXTENSIONS_DEFAULT_MAX_LEN,
+            chunk_extensions_callback: None,
             data_buf,
-            ..Default::default()
         }
     }
+
+    pub fn set_trailers_config(&mut self, config: HeadParseConfig) -> &mut Self {
+        self.trailers_config = config;
+        self
+    }
+
+    pub fn set_chunk_max_len(&mut self, value: usize) -> &mut Self {
+        self.chunk_max_len = value;
+        self
+    }
+    pub fn get_chunk_max_len(&self) -> usize {
+        self.chunk_max_len
+    }
+    pub fn set_chunk_extensions_max_len(&mut self, value: usize) -> &mut Self {
+        self.chunk_extensions_max_len = value;
+        self
+    }
+    pub fn get_chunk_extensions_max_len(&self) -> usize {
+        self.chunk_extensions_max_len
+    }
+
+    /// Called with the chunk-size and the chunk-ext of every chunk that has any,
+    /// including the last-chunk.
+    pub fn set_chunk_extensions_callback(
+        &mut self,
+        callback: Option<ChunkExtensionsCallback>,
+    ) -> &mut Self {
+        self.chunk_extensions_callback = callback;
+        self
+    }
 }
 
 //
@@ -73,10 +117,16 @@ impl BodyParser for ChunkedBodyParser {
         let mut take = r.take(0);
         let mut parsed_num_bytes = 0_usize;
 
+        if self.state == State::Idle {
+            self.trailers.clear();
+            self.trailers_len = 0;
+        }
+
         loop {
             if self.state <= State::WaitLengthParse {
                 let end_bytes_len = 2_usize;
-                take.set_limit(LENGTH_MAX_LEN as u64 + end_bytes_len as u64);
+                let line_max_len = LENGTH_MAX_LEN + self.chunk_extensions_max_len;
+                take.set_limit(line_max_len as u64 + end_bytes_len as u64);
 
                 self.length_buf.clear();
                 let n = take
@@ -87,8 +137,12 @@ impl BodyParser for ChunkedBodyParser {
                     return Ok(BodyParseOutput::Partial(parsed_num_bytes));
                 }
                 if !self.length_buf[..n].ends_with(&[LF]) {
-                    if n >= LENGTH_MAX_LEN {
-                        return Err(BodyParseError::TooLongChunksOfLength);
+                    if n >= line_max_len + end_bytes_len {
+                        if self.length_buf.iter().all(u8::is_ascii_hexdigit) {
+                            return Err(BodyParseError::TooLongChunksOfLength);
+                        } else {
+                            return Err(BodyParseError::TooLongChunksOfExtensions);
+                        }
                     } else {
                         return Ok(BodyParseOutput::Partial(parsed_num_bytes));
                     }
@@ -96,17 +150,41 @@ impl BodyParser for ChunkedBodyParser {
                 if !self.length_buf[..n - 1].ends_with(&[CR]) {
                     return Err(BodyParseError::InvalidCRLF);
                 }
-                let length_bytes = &self.length_buf[..n - end_bytes_len];
+                let line = &self.length_buf[..n - end_bytes_len];
+                // from_str_radix accepts a leading sign, the chunk-size does not.
+                let length_bytes_len = line
+                    .iter()
+                    .position(|x| !x.is_ascii_hexdigit())
+                    .unwrap_or(line.len());
+                let (length_bytes, extensions_bytes) = line.split_at(length_bytes_len);
+                if length_bytes.len() > LENGTH_MAX_LEN {
+                    return Err(BodyParseError::TooLongChunksOfLength);
+                }
+                if extensions_bytes.len() > self.chunk_extensions_max_len {
+                    return Err(BodyParseError::TooLongChunksOfExtensions);
+                }
                 let length_str = core::str::from_utf8(length_bytes)
                     .map_err(|_| BodyParseError::InvalidChunksOfLength(None))?;
-                let length = u16::from_str_radix(length_str, 16)
-                    .map_err(|err| BodyParseError::InvalidChunksOfLength(Some(err)))?;
+                let length =
+                    usize::from_str_radix(length_str, 16).map_err(|err| match err.kind() {
+                        IntErrorKind::PosOverflow => BodyParseError::TooLargeChunksOfLength,
+                        _ => BodyParseError::InvalidChunksOfLength(Some(err)),
+                    })?;
+                if length > self.chunk_max_len {
+                    return Err(BodyParseError::TooLargeChunksOfLength);
+                }
+                if !extensions_bytes.is_empty() {
+                    let extensions = parse_chunk_extensions(extensions_bytes)?;
+                    if let Some(callback) = self.chunk_extensions_callback.as_mut() {
+                        callback(length, &extensions);
+                    }
+                }
 
                 self.length = length;
                 parsed_num_bytes += n;
 
                 if length == 0 {
-                    self.state = State::WaitCRLFParse(ActionAfterCRLFParsed::Break);
+                    self.state = State::WaitTrailerParse;
                 } else {
                     self.state = State::WaitDataParse;
                 }
@@ -120,11 +198,11 @@ impl BodyParser for ChunkedBodyParser {
                     .map_err(BodyParseError::ReadError)?;
                 body_buf.extend_from_slice(&self.data_buf[..n]);
 
-                self.length -= n as u16;
+                self.length -= n;
                 parsed_num_bytes += n;
 
                 if self.length == 0 {
-                    self.state = State::WaitCRLFParse(ActionAfterCRLFParsed::Continue);
+                    self.state = State::WaitCRLFParse;
                 } else {
                     self.state = State::WaitDataParsing;
 
@@ -132,7 +210,7 @@ impl BodyParser for ChunkedBodyParser {
                 }
             }
 
-            if let State::WaitCRLFParse(action) = &self.state {
+            if self.state == State::WaitCRLFParse {
                 let end_bytes_len = 2_usize;
                 take.set_limit(end_bytes_len as u64);
 
@@ -148,16 +226,41 @@ impl BodyParser for ChunkedBodyParser {
                 }
                 parsed_num_bytes += n;
 
-                match action {
-                    ActionAfterCRLFParsed::Continue => {
-                        self.state = State::WaitLengthParse;
+                self.state = State::WaitLengthParse;
 
-                        continue;
-                    }
-                    ActionAfterCRLFParsed::Break => {
-                        self.state = State::Idle;
+                continue;
+            }
+
+            if self.state == State::WaitTrailerParse {
+                loop {
+                    self.length_buf.clear();
+                    match parse_header(
+                        &mut take,
+                        &mut self.length_buf,
+                        &self.trailers_config,
+                        &mut self.trailers,
+                    )
+                    .map_err(|err| match err {
+                        HeadParseError::InvalidCRLF => BodyParseError::InvalidCRLF,
+                        err => BodyParseError::InvalidTrailer(err),
+                    })? {
+                        Some((is_all_completed, n)) => {
+                            parsed_num_bytes += n;
+
+                            if is_all_completed {
+                                self.state = State::Idle;
 
-                        break Ok(BodyParseOutput::Completed(parsed_num_bytes));
+                                return Ok(BodyParseOutput::Completed(parsed_num_bytes));
+                            }
+
+                            self.trailers_len += n;
+                            if self.trailers_len > self.trailers_config.get_headers_max_len() {
+                                return Err(BodyParseError::InvalidTrailer(
+                                    HeadParseError::TooLongHeaders,
+                                ));
+                            }
+                        }
+                        None => return Ok(BodyParseOutput::Partial(parsed_num_bytes)),
                     }
                 }
             }
@@ -166,3 +269,85 @@ impl BodyParser for ChunkedBodyParser {
         }
     }
 }
+
+//
+//
+//
+// chunk-ext = *( BWS ";" BWS chunk-ext-name [ BWS "=" BWS chunk-ext-val ] )
+fn parse_chunk_extensions(bytes: &[u8]) -> Result<Vec<ChunkExtension>, BodyParseError> {
+    let is_bws = |x: &u8| x == &SP || x == &HTAB;
+    let is_tchar = |x: &u8| x.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(x);
+    let skip_bws = |i: &mut usize| {
+        while bytes.get(*i).map(is_bws).unwrap_or(false) {
+            *i += 1;
+        }
+    };
+    let take_token = |i: &mut usize| -> Result<String, BodyParseError> {
+        let start = *i;
+        while bytes.get(*i).map(is_tchar).unwrap_or(false) {
+            *i += 1;
+        }
+        if *i == start {
+            return Err(BodyParseError::InvalidChunksOfExtensions);
+        }
+        // tchar is ASCII
+        Ok(String::from_utf8_lossy(&bytes[start..*i]).into_owned())
+    };
+
+    let mut extensions = vec![];
+    let mut i = 0;
+    loop {
+        skip_bws(&mut i);
+        match bytes.get(i) {
+            None => break,
+            Some(b';') => i += 1,
+            Some(_) => return Err(BodyParseError::InvalidChunksOfExtensions),
+        }
+        skip_bws(&mut i);
+        let name = take_token(&mut i)?;
+
+        let mut j = i;
+        skip_bws(&mut j);
+        let value = if bytes.get(j) == Some(&b'=') {
+            i = j + 1;
+            skip_bws(&mut i);
+            if bytes.get(i) == Some(&b'"') {
+                i += 1;
+                let mut value = vec![];
+                loop {
+                    match bytes.get(i) {
+                        Some(b'"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some(b'\\') => {
+                            value.push(
+                                *bytes
+                                    .get(i + 1)
+                                    .ok_or(BodyParseError::InvalidChunksOfExtensions)?,
+                            );
+                            i += 2;
+                        }
+                        Some(x) => {
+                            value.push(*x);
+                            i += 1;
+                        }
+                        None => return Err(BodyParseError::InvalidChunksOfExtensions),
+                    }
+                }
+                Some(
+                    String::from_utf8(value)
+                        .map_err(|_| BodyParseError::InvalidChunksOfExtensions)?,
+                )
+            } else {
+                Some(take_token(&mut i)?)
+            }
+        } else {
+            None
+        };
+
+        extensions.push((name, value));
+    }
+
+    Ok(extensions)
+}
@@ -0,0 +1,80 @@
+use http::{
+    header::{CONNECTION, UPGRADE as UPGRADE_HEADER},
+    HeaderMap, HeaderValue, Version,
+};
+
+pub const CLOSE: &str = "close";
+pub const KEEP_ALIVE: &str = "keep-alive";
+pub const UPGRADE: &str = "upgrade";
+
+//
+//
+//
+// ref https://www.rfc-editor.org/rfc/rfc9112#section-9.3
+pub fn is_persistent(headers: &HeaderMap<HeaderValue>, version: &Version) -> bool {
+    let mut is_keep_alive = false;
+    for option in connection_options(headers) {
+        if option.eq_ignore_ascii_case(CLOSE) {
+            return false;
+        }
+        if option.eq_ignore_ascii_case(KEEP_ALIVE) {
+            is_keep_alive = true;
+        }
+    }
+
+    match *version {
+        Version::HTTP_09 => false,
+        Version::HTTP_10 => is_keep_alive,
+        _ => true,
+    }
+}
+
+// ref https://www.rfc-editor.org/rfc/rfc9110#section-7.8
+pub fn is_upgrade(headers: &HeaderMap<HeaderValue>) -> bool {
+    headers.contains_key(UPGRADE_HEADER)
+        && connection_options(headers).any(|x| x.eq_ignore_ascii_case(UPGRADE))
+}
+
+fn connection_options(headers: &HeaderMap<HeaderValue>) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+        .map(|x| x.trim_matches(&[' ', '\t'][..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_persistent() {
+        let mut header_map = HeaderMap::new();
+        assert!(is_persistent(&header_map, &Version::HTTP_11));
+        assert!(!is_persistent(&header_map, &Version::HTTP_10));
+
+        header_map.insert("Connection", "Keep-Alive".parse().unwrap());
+        assert!(is_persistent(&header_map, &Version::HTTP_10));
+
+        header_map.insert("Connection", "Upgrade, Close".parse().unwrap());
+        assert!(!is_persistent(&header_map, &Version::HTTP_11));
+
+        header_map.insert("Connection", "keep-alive".parse().unwrap());
+        header_map.append("Connection", "close".parse().unwrap());
+        assert!(!is_persistent(&header_map, &Version::HTTP_10));
+    }
+
+    #[test]
+    fn test_is_upgrade() {
+        let mut header_map = HeaderMap::new();
+        header_map.insert("Connection", "keep-alive, Upgrade".parse().unwrap());
+        assert!(!is_upgrade(&header_map));
+
+        header_map.insert("Upgrade", "websocket".parse().unwrap());
+        assert!(is_upgrade(&header_map));
+
+        header_map.remove("Connection");
+        assert!(!is_upgrade(&header_map));
+    }
+}
@@ -2,7 +2,10 @@ use core::cmp::min;
 use std::io::{BufRead, Error as IoError, ErrorKind as IoErrorKind, Take};
 
 use http::{
-    header::{HeaderName, InvalidHeaderName, InvalidHeaderValue},
+    header::{
+        HeaderName, InvalidHeaderName, InvalidHeaderValue, AUTHORIZATION, CONTENT_LENGTH,
+        CONTENT_TYPE, HOST, LOCATION, PROXY_AUTHORIZATION,
+    },
     method::InvalidMethod,
     status::InvalidStatusCode,
     uri::InvalidUri,
@@ -10,6 +13,7 @@ use http::{
 };
 
 use crate::{
+    body_framing::{BodyFraming, BodyFramingDetectError, BodyFramingDetector as _},
     ReasonPhrase, COLON, CR, HTTP_VERSION_10, HTTP_VERSION_11, HTTP_VERSION_2, HTTP_VERSION_20,
     HTTP_VERSION_3, HTTP_VERSION_30, LF, SP,
 };
@@ -23,6 +27,16 @@ const STATUS_CODE_LEN: usize = 3;
 const HEADERS_MAX_LEN: usize = 8192;
 const URI_MAX_LEN: usize = 2048;
 
+// Fields that must not appear more than once in a message.
+const SINGLETON_HEADERS: &[HeaderName] = &[
+    HOST,
+    CONTENT_LENGTH,
+    CONTENT_TYPE,
+    AUTHORIZATION,
+    PROXY_AUTHORIZATION,
+    LOCATION,
+];
+
 pub type IsAllCompleted = bool;
 
 //
@@ -32,22 +46,28 @@ pub type IsAllCompleted = bool;
 pub struct HeadParseConfig {
     header_max_len: usize,
     headers_max_len: usize,
+    headers_max_count: usize,
     // res
     reason_phrase_max_len: usize,
     // req
     method_max_len: usize,
     uri_max_len: usize,
+    //
+    reject_duplicate_singleton_headers: bool,
 }
 impl Default for HeadParseConfig {
     fn default() -> Self {
         HeadParseConfig {
             header_max_len: 32 + 448,
             headers_max_len: 4096,
+            headers_max_count: 100,
             // res
             reason_phrase_max_len: 40,
             // req
             method_max_len: 8,
             uri_max_len: 512,
+            //
+            reject_duplicate_singleton_headers: false,
         }
     }
 }
@@ -77,6 +97,13 @@ impl HeadParseConfig {
     pub fn get_headers_max_len(&self) -> usize {
         self.headers_max_len
     }
+    pub fn set_headers_max_count(&mut self, value: u16) -> &mut Self {
+        self.headers_max_count = value as usize;
+        self
+    }
+    pub fn get_headers_max_count(&self) -> usize {
+        self.headers_max_count
+    }
     // res
     pub fn set_reason_phrase_max_len(&mut self, value: u8) -> &mut Self {
         self.reason_phrase_max_len = value as usize;
@@ -100,6 +127,14 @@ impl HeadParseConfig {
     pub fn get_uri_max_len(&self) -> usize {
         self.uri_max_len
     }
+    //
+    pub fn set_reject_duplicate_singleton_headers(&mut self, value: bool) -> &mut Self {
+        self.reject_duplicate_singleton_headers = value;
+        self
+    }
+    pub fn get_reject_duplicate_singleton_headers(&self) -> bool {
+        self.reject_duplicate_singleton_headers
+    }
 }
 
 //
@@ -121,6 +156,8 @@ pub enum HeadParseError {
     InvalidHeaderName(InvalidHeaderName),
     InvalidHeaderValue(InvalidHeaderValue),
     TooLongHeaders,
+    TooManyHeaders,
+    DuplicateHeader(HeaderName),
     InvalidCRLF,
     // res
     TooLongStatusCode,
@@ -140,7 +177,7 @@ impl core::fmt::Display for HeadParseError {
 impl std::error::Error for HeadParseError {}
 impl From<HeadParseError> for IoError {
     fn from(err: HeadParseError) -> IoError {
-        IoError::new(IoErrorKind::InvalidInput, err.to_string())
+        IoError::new(IoErrorKind::InvalidInput, err)
     }
 }
 
@@ -156,55 +193,17 @@ pub trait HeadParser {
 
     fn parse<R: BufRead>(&mut self, r: &mut R) -> Result<HeadParseOutput, HeadParseError>;
 
+    fn detect_body_framing(&self) -> Result<BodyFraming, BodyFramingDetectError> {
+        (self.get_headers(), self.get_version()).detect()
+    }
+
     fn parse_header<R: BufRead>(
         take: &mut Take<R>,
         buf: &mut Vec<u8>,
         config: &HeadParseConfig,
         headers: &mut HeaderMap<HeaderValue>,
     ) -> Result<Option<(IsAllCompleted, usize)>, HeadParseError> {
-        let end_bytes_len = 2_usize;
-        take.set_limit(config.get_header_max_len() as u64 + end_bytes_len as u64);
-        let n = take
-            .read_until(LF, buf)
-            .map_err(HeadParseError::ReadError)?;
-        if n < end_bytes_len {
-            return Ok(None);
-        }
-        if !buf[..n].ends_with(&[LF]) {
-            if n >= config.get_header_max_len() {
-                return Err(HeadParseError::TooLongHeader);
-            } else {
-                return Ok(None);
-            }
-        }
-        if !buf[..n - 1].ends_with(&[CR]) {
-            return Err(HeadParseError::InvalidCRLF);
-        }
-
-        // TODO, valid HEADERS_MAX_LEN
-
-        //
-        if buf[..n - end_bytes_len].is_empty() {
-            return Ok(Some((true, n)));
-        }
-        let header_colon_index = buf[..n - end_bytes_len]
-            .iter()
-            .position(|x| x == &COLON)
-            .ok_or(HeadParseError::InvalidHeader)?;
-        let header_name = &buf[..header_colon_index];
-        let header_value = &buf[header_colon_index + 1..n - end_bytes_len];
-        let mut n_left_whitespace = 0_usize;
-        if header_value[0] == SP {
-            n_left_whitespace += 1;
-        }
-
-        let header_name =
-            HeaderName::from_bytes(header_name).map_err(HeadParseError::InvalidHeaderName)?;
-        let header_value = HeaderValue::from_bytes(&header_value[n_left_whitespace..])
-            .map_err(HeadParseError::InvalidHeaderValue)?;
-
-        headers.insert(header_name, header_value);
-        Ok(Some((false, n)))
+        parse_header(take, buf, config, headers)
     }
 
     //
@@ -223,7 +222,7 @@ pub trait HeadParser {
             return Ok(None);
         }
         if !buf[..n].ends_with(&[SP]) {
-            if n >= HTTP_VERSION_LEN {
+            if n >= HTTP_VERSION_LEN + end_bytes_len {
                 return Err(HeadParseError::TooLongHttpVersion);
             } else {
                 return Ok(None);
@@ -252,7 +251,7 @@ pub trait HeadParser {
             return Ok(None);
         }
         if !buf[..n].ends_with(&[SP]) {
-            if n >= STATUS_CODE_LEN {
+            if n >= STATUS_CODE_LEN + end_bytes_len {
                 return Err(HeadParseError::TooLongStatusCode);
             } else {
                 return Ok(None);
@@ -278,7 +277,7 @@ pub trait HeadParser {
             return Ok(None);
         }
         if !buf[..n].ends_with(&[LF]) {
-            if n >= config.get_reason_phrase_max_len() {
+            if n >= config.get_reason_phrase_max_len() + end_bytes_len {
                 return Err(HeadParseError::TooLongReasonPhrase);
             } else {
                 return Ok(None);
@@ -313,7 +312,7 @@ pub trait HeadParser {
             return Ok(None);
         }
         if !buf[..n].ends_with(&[SP]) {
-            if n >= config.get_method_max_len() {
+            if n >= config.get_method_max_len() + end_bytes_len {
                 return Err(HeadParseError::TooLongMethod);
             } else {
                 return Ok(None);
@@ -339,7 +338,7 @@ pub trait HeadParser {
             return Ok(None);
         }
         if !buf[..n].ends_with(&[SP]) {
-            if n >= config.get_uri_max_len() {
+            if n >= config.get_uri_max_len() + end_bytes_len {
                 return Err(HeadParseError::TooLongUri);
             } else {
                 return Ok(None);
@@ -365,7 +364,7 @@ pub trait HeadParser {
             return Ok(None);
         }
         if !buf[..n].ends_with(&[LF]) {
-            if n >= HTTP_VERSION_LEN {
+            if n >= HTTP_VERSION_LEN + end_bytes_len {
                 return Err(HeadParseError::TooLongHttpVersion);
             } else {
                 return Ok(None);
@@ -385,6 +384,71 @@ pub trait HeadParser {
     }
 }
 
+//
+//
+//
+pub fn parse_header<R: BufRead>(
+    take: &mut Take<R>,
+    buf: &mut Vec<u8>,
+    config: &HeadParseConfig,
+    headers: &mut HeaderMap<HeaderValue>,
+) -> Result<Option<(IsAllCompleted, usize)>, HeadParseError> {
+    let end_bytes_len = 2_usize;
+    take.set_limit(config.get_header_max_len() as u64 + end_bytes_len as u64);
+    let n = take
+        .read_until(LF, buf)
+        .map_err(HeadParseError::ReadError)?;
+    if n < end_bytes_len {
+        return Ok(None);
+    }
+    if !buf[..n].ends_with(&[LF]) {
+        if n >= config.get_header_max_len() + end_bytes_len {
+            return Err(HeadParseError::TooLongHeader);
+        } else {
+            return Ok(None);
+        }
+    }
+    if !buf[..n - 1].ends_with(&[CR]) {
+        return Err(HeadParseError::InvalidCRLF);
+    }
+
+    // HEADERS_MAX_LEN is checked by the caller, it knows the size of the previous headers.
+
+    //
+    if buf[..n - end_bytes_len].is_empty() {
+        return Ok(Some((true, n)));
+    }
+    let header_colon_index = buf[..n - end_bytes_len]
+        .iter()
+        .position(|x| x == &COLON)
+        .ok_or(HeadParseError::InvalidHeader)?;
+    let header_name = &buf[..header_colon_index];
+    let header_value = &buf[header_colon_index + 1..n - end_bytes_len];
+    let mut n_left_whitespace = 0_usize;
+    if header_value.first() == Some(&SP) {
+        n_left_whitespace += 1;
+    }
+
+    let header_name =
+        HeaderName::from_bytes(header_name).map_err(HeadParseError::InvalidHeaderName)?;
+    let header_value = HeaderValue::from_bytes(&header_value[n_left_whitespace..])
+        .map_err(HeadParseError::InvalidHeaderValue)?;
+
+    if config.get_reject_duplicate_singleton_headers()
+        && SINGLETON_HEADERS.contains(&header_name)
+        && headers.contains_key(&header_name)
+    {
+        return Err(HeadParseError::DuplicateHeader(header_name));
+    }
+
+    if headers.len() >= config.get_headers_max_count() {
+        return Err(HeadParseError::TooManyHeaders);
+    }
+
+    headers.append(header_name, header_value);
+    Ok(Some((false, n)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -415,4 +479,22 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn parse_header_with_empty_value() -> Result<(), Box<dyn std::error::Error>> {
+        let mut take = BufReader::new(Cursor::new(b"Foo:\r\n")).take(0);
+        let mut buf = Vec::new();
+        let mut headers = HeaderMap::new();
+
+        parse_header(
+            &mut take,
+            &mut buf,
+            &HeadParseConfig::default(),
+            &mut headers,
+        )?;
+
+        assert_eq!(headers.get("Foo").unwrap(), "");
+
+        Ok(())
+    }
 }
@@ -6,6 +6,7 @@ pub use http;
 pub mod body_framing;
 pub mod body_parser;
 pub mod chunked_body_parser;
+pub mod connection;
 pub mod content_length_body_parser;
 pub mod head_parser;
 pub mod head_renderer;
@@ -18,6 +19,7 @@ pub mod response_head_renderer;
 //
 //
 pub const SP: u8 = b' ';
+pub const HTAB: u8 = b'\t';
 pub const HTTP_VERSION_10: &[u8] = b"HTTP/1.0";
 pub const HTTP_VERSION_11: &[u8] = b"HTTP/1.1";
 pub const HTTP_VERSION_20: &[u8] = b"HTTP/2.0";
@@ -32,3 +34,4 @@ pub const CRLF: &[u8] = b"\r\n";
 pub type ReasonPhrase = Option<Vec<u8>>;
 
 pub const CHUNKED: &str = "chunked";
+pub const LAST_CHUNK: &[u8] = b"0\r\n";
@@ -18,21 +18,18 @@ pub struct RequestHeadParser {
     //
     state: State,
     buf: Vec<u8>,
+    headers_len: usize,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
 enum State {
+    #[default]
     Idle,
     MethodParsed,
     UriParsed,
     HttpVersionParsed,
     HeadersParsing,
 }
-impl Default for State {
-    fn default() -> Self {
-        Self::Idle
-    }
-}
 
 impl RequestHeadParser {
     pub fn to_request_parts(&self) -> RequestParts {
@@ -124,6 +121,7 @@ impl HeadParser for RequestHeadParser {
         // headers
         if self.state < State::HeadersParsing {
             self.headers.clear();
+            self.headers_len = 0;
         }
         loop {
             if self.state <= State::HeadersParsing {
@@ -140,6 +138,11 @@ impl HeadParser for RequestHeadParser {
                         } else {
                             self.state = State::HeadersParsing;
 
+                            self.headers_len += n;
+                            if self.headers_len > self.config.get_headers_max_len() {
+                                return Err(HeadParseError::TooLongHeaders);
+                            }
+
                             continue;
                         }
                     }
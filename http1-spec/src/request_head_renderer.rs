@@ -1,6 +1,6 @@
 use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 
-use http::{request::Parts, Request, Version};
+use http::{request::Parts, Method, Request, Uri, Version};
 
 use crate::{
     head_renderer::HeadRenderer, COLON, CRLF, HTTP_VERSION_10, HTTP_VERSION_11, HTTP_VERSION_2,
@@ -37,7 +37,11 @@ impl HeadRenderer<Parts> for RequestHeadRenderer {
 
         buf.extend_from_slice(parts.method.as_str().as_bytes());
         buf.extend_from_slice(&[SP]);
-        buf.extend_from_slice(parts.uri.to_string().as_bytes());
+        if parts.method == Method::CONNECT {
+            buf.extend_from_slice(authority_form(&parts.uri)?.as_bytes());
+        } else {
+            buf.extend_from_slice(parts.uri.to_string().as_bytes());
+        }
         buf.extend_from_slice(&[SP]);
         buf.extend_from_slice(version_bytes);
         buf.extend_from_slice(CRLF);
@@ -54,3 +58,23 @@ impl HeadRenderer<Parts> for RequestHeadRenderer {
         Ok(())
     }
 }
+
+// ref https://www.rfc-editor.org/rfc/rfc9112#section-3.2.3
+fn authority_form(uri: &Uri) -> Result<String, IoError> {
+    let authority = uri
+        .authority()
+        .ok_or_else(|| IoError::new(IoErrorKind::InvalidInput, "CONNECT without authority"))?;
+    let port = match (authority.port_u16(), uri.scheme_str()) {
+        (Some(port), _) => port,
+        (None, Some("http")) => 80,
+        (None, Some("https")) => 443,
+        _ => {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "CONNECT without port",
+            ))
+        }
+    };
+    // Without userinfo.
+    Ok(format!("{}:{port}", authority.host()))
+}
@@ -1,10 +1,11 @@
 use std::io::{BufRead, Read as _};
 
 use http::{
-    response::Parts as ResponseParts, HeaderMap, HeaderValue, Response, StatusCode, Version,
+    response::Parts as ResponseParts, HeaderMap, HeaderValue, Method, Response, StatusCode, Version,
 };
 
 use crate::{
+    body_framing::{BodyFraming, BodyFramingDetectError, BodyFramingDetector as _},
     head_parser::{HeadParseConfig, HeadParseError, HeadParseOutput, HeadParser},
     ReasonPhrase,
 };
@@ -20,24 +21,22 @@ pub struct ResponseHeadParser {
     pub headers: HeaderMap<HeaderValue>,
     //
     config: HeadParseConfig,
+    request_method: Option<Method>,
     //
     state: State,
     buf: Vec<u8>,
+    headers_len: usize,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
 enum State {
+    #[default]
     Idle,
     HttpVersionParsed,
     StatusCodeParsed,
     ReasonPhraseParsed,
     HeadersParsing,
 }
-impl Default for State {
-    fn default() -> Self {
-        Self::Idle
-    }
-}
 
 impl ResponseHeadParser {
     pub fn to_response_parts(&self) -> ResponseParts {
@@ -53,6 +52,14 @@ impl ResponseHeadParser {
         let parts = self.to_response_parts();
         Response::from_parts(parts, body)
     }
+
+    /// The method of the request being answered, it decides whether the response has a body.
+    pub fn set_request_method(&mut self, method: Option<Method>) {
+        self.request_method = method;
+    }
+    pub fn get_request_method(&self) -> Option<&Method> {
+        self.request_method.as_ref()
+    }
 }
 
 //
@@ -80,6 +87,13 @@ impl HeadParser for ResponseHeadParser {
         &self.http_version
     }
 
+    fn detect_body_framing(&self) -> Result<BodyFraming, BodyFramingDetectError> {
+        match &self.request_method {
+            Some(method) => (&self.headers, &self.http_version, &self.status_code, method).detect(),
+            None => (&self.headers, &self.http_version, &self.status_code).detect(),
+        }
+    }
+
     fn parse<R: BufRead>(&mut self, r: &mut R) -> Result<HeadParseOutput, HeadParseError> {
         let mut take = r.take(0);
         let mut parsed_num_bytes = 0_usize;
@@ -129,6 +143,7 @@ impl HeadParser for ResponseHeadParser {
         // headers
         if self.state < State::HeadersParsing {
             self.headers.clear();
+            self.headers_len = 0;
         }
         loop {
             if self.state <= State::HeadersParsing {
@@ -145,6 +160,11 @@ impl HeadParser for ResponseHeadParser {
                         } else {
                             self.state = State::HeadersParsing;
 
+                            self.headers_len += n;
+                            if self.headers_len > self.config.get_headers_max_len() {
+                                return Err(HeadParseError::TooLongHeaders);
+                            }
+
                             continue;
                         }
                     }
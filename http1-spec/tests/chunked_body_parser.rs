@@ -1,9 +1,13 @@
 use core::str;
-use std::io::{BufReader, Cursor};
+use std::{
+    io::{BufReader, Cursor},
+    sync::{Arc, Mutex},
+};
 
 use http1_spec::{
     body_parser::{BodyParseError, BodyParseOutput, BodyParser},
     chunked_body_parser::ChunkedBodyParser,
+    head_parser::HeadParseError,
 };
 
 #[test]
@@ -219,3 +223,270 @@ fn full() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn large_chunk() -> Result<(), Box<dyn std::error::Error>> {
+    // 1 MiB, e.g. nginx
+    let data = vec![b'x'; 0x100000];
+
+    let mut bytes = b"100000\r\n".to_vec();
+    bytes.extend_from_slice(&data);
+    bytes.extend_from_slice(b"\r\n0\r\n\r\n");
+
+    let mut p = ChunkedBodyParser::with_data_buf(vec![0u8; 64 * 1024]);
+
+    let mut r = BufReader::new(Cursor::new(bytes));
+    let mut body_buf = vec![];
+    loop {
+        match p.parse(&mut r, &mut body_buf)? {
+            BodyParseOutput::Completed(_) => break,
+            BodyParseOutput::Partial(n) => assert!(n > 0),
+        }
+    }
+    assert_eq!(body_buf, data);
+
+    Ok(())
+}
+
+#[test]
+fn too_large_chunk() -> Result<(), Box<dyn std::error::Error>> {
+    let mut p = ChunkedBodyParser::new();
+    p.set_chunk_max_len(1024);
+
+    let mut body_buf = vec![];
+    let err = p
+        .parse(&mut BufReader::new(Cursor::new(b"401\r\n")), &mut body_buf)
+        .err()
+        .unwrap();
+    match err {
+        BodyParseError::TooLargeChunksOfLength => {}
+        err => panic!("err not match, err:{err}"),
+    }
+
+    let mut p = ChunkedBodyParser::new();
+
+    let err = p
+        .parse(
+            &mut BufReader::new(Cursor::new(b"000000000000000000001\r\n")),
+            &mut body_buf,
+        )
+        .err()
+        .unwrap();
+    match err {
+        BodyParseError::TooLongChunksOfLength => {}
+        err => panic!("err not match, err:{err}"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn overflowing_chunk() -> Result<(), Box<dyn std::error::Error>> {
+    let mut p = ChunkedBodyParser::new();
+
+    let mut body_buf = vec![];
+    let err = p
+        .parse(
+            &mut BufReader::new(Cursor::new(b"100000000000000000\r\n")),
+            &mut body_buf,
+        )
+        .err()
+        .unwrap();
+    match err {
+        BodyParseError::TooLargeChunksOfLength => {}
+        err => panic!("err not match, err:{err}"),
+    }
+
+    let mut p = ChunkedBodyParser::new();
+
+    let o = p.parse(
+        &mut BufReader::new(Cursor::new(b"00001\r\nx\r\n0\r\n\r\n")),
+        &mut body_buf,
+    )?;
+    assert_eq!(o, BodyParseOutput::Completed(15));
+    assert_eq!(body_buf, b"x".to_vec());
+
+    Ok(())
+}
+
+#[test]
+fn invalid_chunk_length() -> Result<(), Box<dyn std::error::Error>> {
+    for bytes in [&b"+5\r\n"[..], b"-5\r\n", b"x\r\n", b"\r\n"] {
+        let mut p = ChunkedBodyParser::new();
+
+        let mut body_buf = vec![];
+        let err = p
+            .parse(&mut BufReader::new(Cursor::new(bytes)), &mut body_buf)
+            .err()
+            .unwrap();
+        match err {
+            BodyParseError::InvalidChunksOfLength(_) => {}
+            err => panic!("err not match, err:{err}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn with_extensions() -> Result<(), Box<dyn std::error::Error>> {
+    let extensions = Arc::new(Mutex::new(vec![]));
+
+    let mut p = ChunkedBodyParser::new();
+    let extensions_cloned = extensions.clone();
+    p.set_chunk_extensions_callback(Some(Box::new(move |length, exts| {
+        extensions_cloned
+            .lock()
+            .unwrap()
+            .push((length, exts.to_vec()));
+    })));
+
+    let mut body_buf = vec![];
+    let o = p.parse(
+        &mut BufReader::new(Cursor::new(
+            &b"4;name=value\r\nWiki\r\n5 ; foo ; sig=\"a\\\"b c\"\r\npedia\r\n0;last\r\n\r\n"[..],
+        )),
+        &mut body_buf,
+    )?;
+    assert_eq!(o, BodyParseOutput::Completed(61));
+    assert_eq!(body_buf, b"Wikipedia".to_vec());
+
+    assert_eq!(
+        *extensions.lock().unwrap(),
+        vec![
+            (4, vec![("name".to_owned(), Some("value".to_owned()))]),
+            (
+                5,
+                vec![
+                    ("foo".to_owned(), None),
+                    ("sig".to_owned(), Some("a\"b c".to_owned()))
+                ]
+            ),
+            (0, vec![("last".to_owned(), None)]),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn invalid_extensions() -> Result<(), Box<dyn std::error::Error>> {
+    for bytes in [
+        &b"4;\r\n"[..],
+        b"4;=x\r\n",
+        b"4;a=\r\n",
+        b"4;a=\"x\r\n",
+        b"4 x\r\n",
+    ] {
+        let mut p = ChunkedBodyParser::new();
+
+        let mut body_buf = vec![];
+        let err = p
+            .parse(&mut BufReader::new(Cursor::new(bytes)), &mut body_buf)
+            .err()
+            .unwrap();
+        match err {
+            BodyParseError::InvalidChunksOfExtensions => {}
+            err => panic!("err not match, err:{err}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn too_long_extensions() -> Result<(), Box<dyn std::error::Error>> {
+    let mut p = ChunkedBodyParser::new();
+    p.set_chunk_extensions_max_len(8);
+
+    let mut body_buf = vec![];
+    let o = p.parse(
+        &mut BufReader::new(Cursor::new(b"1;a=1234\r\nx\r\n")),
+        &mut body_buf,
+    )?;
+    assert_eq!(o, BodyParseOutput::Partial(13));
+
+    let err = p
+        .parse(
+            &mut BufReader::new(Cursor::new(b"1;a=123456\r\nx\r\n")),
+            &mut body_buf,
+        )
+        .err()
+        .unwrap();
+    match err {
+        BodyParseError::TooLongChunksOfExtensions => {}
+        err => panic!("err not match, err:{err}"),
+    }
+
+    let mut p = ChunkedBodyParser::new();
+    p.set_chunk_extensions_max_len(8);
+
+    let err = p
+        .parse(
+            &mut BufReader::new(Cursor::new(b"1;a=1234567890123456789012345678901234567890")),
+            &mut body_buf,
+        )
+        .err()
+        .unwrap();
+    match err {
+        BodyParseError::TooLongChunksOfExtensions => {}
+        err => panic!("err not match, err:{err}"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn with_trailers() -> Result<(), Box<dyn std::error::Error>> {
+    let mut p = ChunkedBodyParser::new();
+
+    let bytes = b"4\r\nWiki\r\n0\r\nChecksum: abc\r\nGrpc-Status: 0\r\n\r\nfoo";
+
+    let mut body_buf = vec![];
+    let o = p.parse(
+        &mut BufReader::new(Cursor::new(&bytes[..20])),
+        &mut body_buf,
+    )?;
+    assert_eq!(o, BodyParseOutput::Partial(12));
+    assert_eq!(p.trailers.len(), 0);
+
+    let o = p.parse(
+        &mut BufReader::new(Cursor::new(&bytes[12..])),
+        &mut body_buf,
+    )?;
+    assert_eq!(o, BodyParseOutput::Completed(33));
+    assert_eq!(body_buf, b"Wiki".to_vec());
+    assert_eq!(p.trailers.len(), 2);
+    assert_eq!(p.trailers.get("Checksum").unwrap(), "abc");
+    assert_eq!(p.trailers.get("Grpc-Status").unwrap(), "0");
+
+    // again
+    let o = p.parse(
+        &mut BufReader::new(Cursor::new(b"0\r\n\r\n")),
+        &mut body_buf,
+    )?;
+    assert_eq!(o, BodyParseOutput::Completed(5));
+    assert_eq!(p.trailers.len(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn invalid_trailers() -> Result<(), Box<dyn std::error::Error>> {
+    let mut p = ChunkedBodyParser::new();
+
+    let mut body_buf = vec![];
+    let err = p
+        .parse(
+            &mut BufReader::new(Cursor::new(b"0\r\nChecksum abc\r\n\r\n")),
+            &mut body_buf,
+        )
+        .err()
+        .unwrap();
+    match err {
+        BodyParseError::InvalidTrailer(HeadParseError::InvalidHeader) => {}
+        err => panic!("err not match, err:{err}"),
+    }
+
+    Ok(())
+}
@@ -1,9 +1,9 @@
 use std::io::{BufReader, Cursor};
 
-use http::{Method, Version};
+use http::{header::HOST, Method, Version};
 
 use http1_spec::{
-    head_parser::{HeadParseOutput, HeadParser},
+    head_parser::{HeadParseConfig, HeadParseError, HeadParseOutput, HeadParser},
     request_head_parser::RequestHeadParser,
 };
 
@@ -22,6 +22,20 @@ fn simple() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn partial() -> Result<(), Box<dyn std::error::Error>> {
+    let mut p = RequestHeadParser::with_config(Default::default());
+
+    let o = p.parse(&mut BufReader::new(Cursor::new(b"GET / HTTP/1.1\r")))?;
+    assert_eq!(o, HeadParseOutput::Partial(6));
+
+    let o = p.parse(&mut BufReader::new(Cursor::new(b"HTTP/1.1\r\n\r\n")))?;
+    assert_eq!(o, HeadParseOutput::Completed(12));
+    assert_eq!(p.http_version, Version::HTTP_11);
+
+    Ok(())
+}
+
 #[test]
 fn version_http2() -> Result<(), Box<dyn std::error::Error>> {
     let mut p = RequestHeadParser::with_config(Default::default());
@@ -161,3 +175,96 @@ fn full() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn with_duplicate_singleton_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = b"GET / HTTP/1.1\r\nHost: foo.com\r\nHost: bar.com\r\n\r\n";
+
+    let mut p = RequestHeadParser::with_config(Default::default());
+
+    let o = p.parse(&mut BufReader::new(Cursor::new(&bytes[..])))?;
+    assert_eq!(o, HeadParseOutput::Completed(48));
+    assert_eq!(p.headers.get_all("Host").iter().count(), 2);
+
+    let mut config = HeadParseConfig::default();
+    config.set_reject_duplicate_singleton_headers(true);
+    let mut p = RequestHeadParser::with_config(config.clone());
+
+    let err = p
+        .parse(&mut BufReader::new(Cursor::new(&bytes[..])))
+        .err()
+        .unwrap();
+    match err {
+        HeadParseError::DuplicateHeader(name) => assert_eq!(name, HOST),
+        err => panic!("err not match, err:{err}"),
+    }
+
+    let mut p = RequestHeadParser::with_config(config);
+
+    let o = p.parse(&mut BufReader::new(Cursor::new(
+        &b"GET / HTTP/1.1\r\nHost: foo.com\r\nCookie: a=1\r\nCookie: b=2\r\n\r\n"[..],
+    )))?;
+    assert_eq!(o, HeadParseOutput::Completed(59));
+
+    Ok(())
+}
+
+#[test]
+fn too_long_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = HeadParseConfig::default();
+    config.set_headers_max_len(32);
+
+    let bytes = b"GET / HTTP/1.1\r\nHost: foo.com\r\nX-A: 0123456789\r\nX-B: 1\r\n\r\n";
+
+    let mut p = RequestHeadParser::with_config(config.clone());
+
+    let o = p.parse(&mut BufReader::new(Cursor::new(&bytes[..40])))?;
+    assert_eq!(o, HeadParseOutput::Partial(31));
+
+    let err = p
+        .parse(&mut BufReader::new(Cursor::new(&bytes[31..])))
+        .err()
+        .unwrap();
+    match err {
+        HeadParseError::TooLongHeaders => {}
+        err => panic!("err not match, err:{err}"),
+    }
+
+    // again, 31 bytes of headers
+    let mut p = RequestHeadParser::with_config(config);
+
+    let o = p.parse(&mut BufReader::new(Cursor::new(
+        &b"GET / HTTP/1.1\r\nHost: foo.com\r\nX-B: 1\r\n\r\n"[..],
+    )))?;
+    assert_eq!(o, HeadParseOutput::Completed(41));
+
+    Ok(())
+}
+
+#[test]
+fn too_many_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = HeadParseConfig::default();
+    config.set_headers_max_count(2);
+
+    let mut p = RequestHeadParser::with_config(config.clone());
+
+    let o = p.parse(&mut BufReader::new(Cursor::new(
+        &b"GET / HTTP/1.1\r\nA: 1\r\nA: 2\r\n\r\n"[..],
+    )))?;
+    assert_eq!(o, HeadParseOutput::Completed(30));
+
+    let mut p = RequestHeadParser::with_config(config);
+
+    let err = p
+        .parse(&mut BufReader::new(Cursor::new(
+            &b"GET / HTTP/1.1\r\nA: 1\r\nA: 2\r\nB: 3\r\n\r\n"[..],
+        )))
+        .err()
+        .unwrap();
+    match err {
+        HeadParseError::TooManyHeaders => {}
+        err => panic!("err not match, err:{err}"),
+    }
+
+    Ok(())
+}
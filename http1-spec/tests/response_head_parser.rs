@@ -3,7 +3,7 @@ use std::io::{BufReader, Cursor};
 use http::{StatusCode, Version};
 
 use http1_spec::{
-    head_parser::{HeadParseOutput, HeadParser},
+    head_parser::{HeadParseConfig, HeadParseError, HeadParseOutput, HeadParser},
     response_head_parser::ResponseHeadParser,
 };
 
@@ -175,3 +175,53 @@ fn full() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn with_repeated_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let mut p = ResponseHeadParser::with_config(Default::default());
+
+    let o = p.parse(&mut BufReader::new(Cursor::new(
+        b"HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nVia: 1.1 foo\r\nSet-Cookie: b=2\r\n\r\n",
+    )))?;
+    assert_eq!(o, HeadParseOutput::Completed(67));
+
+    assert_eq!(p.headers.len(), 3);
+    assert_eq!(
+        p.headers
+            .get_all("Set-Cookie")
+            .iter()
+            .map(|x| x.to_str().unwrap())
+            .collect::<Vec<_>>(),
+        vec!["a=1", "b=2"]
+    );
+    assert_eq!(p.headers.get("Via").unwrap().to_str().unwrap(), "1.1 foo");
+
+    Ok(())
+}
+
+#[test]
+fn too_long_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = HeadParseConfig::default();
+    config.set_headers_max_len(20);
+
+    let mut p = ResponseHeadParser::with_config(config);
+
+    let bytes = b"HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nSet-Cookie: b=2\r\n\r\n";
+
+    let o = p.parse(&mut BufReader::new(Cursor::new(&bytes[..20])))?;
+    assert_eq!(o, HeadParseOutput::Partial(17));
+
+    let o = p.parse(&mut BufReader::new(Cursor::new(&bytes[17..40])))?;
+    assert_eq!(o, HeadParseOutput::Partial(17));
+
+    let err = p
+        .parse(&mut BufReader::new(Cursor::new(&bytes[34..])))
+        .err()
+        .unwrap();
+    match err {
+        HeadParseError::TooLongHeaders => {}
+        err => panic!("err not match, err:{err}"),
+    }
+
+    Ok(())
+}